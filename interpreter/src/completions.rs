@@ -0,0 +1,267 @@
+//! Generates shell completions from the main or modes section of a script, without running it
+
+use clap::ValueEnum;
+use leviscript_lib::core::*;
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum Shell {
+    Bash,
+    Zsh,
+    Fish,
+}
+
+/// The arguments that a script declares
+pub enum Interface<'a> {
+    Main(&'a ArgSpec),
+    Modes(&'a ModesSpec, Vec<&'a ArgSpec>),
+}
+
+/// main and modes sections are only allowed at the top level of a script
+pub fn find_interface(ast: &Block) -> Option<Interface<'_>> {
+    ast.1.iter().find_map(|phrase| match phrase {
+        Phrase::Main(main) => Some(Interface::Main(&main.spec)),
+        Phrase::Modes(modes) => Some(Interface::Modes(
+            &modes.spec,
+            modes.modes.iter().map(|m| &m.spec).collect(),
+        )),
+        Phrase::Expr(_) => None,
+    })
+}
+
+/// `cmd_name` is the name under which the script is called
+pub fn generate(shell: Shell, cmd_name: &str, interface: &Interface) -> String {
+    match shell {
+        Shell::Bash => bash(cmd_name, interface),
+        Shell::Zsh => zsh(cmd_name, interface),
+        Shell::Fish => fish(cmd_name, interface),
+    }
+}
+
+fn bash(cmd_name: &str, interface: &Interface) -> String {
+    let fn_name = format!("_levis_{}", identifier(cmd_name));
+    let specs = match interface {
+        Interface::Main(spec) => vec![*spec],
+        Interface::Modes(_, specs) => specs.clone(),
+    };
+    // the shared options are part of every mode
+    let mut value_options: Vec<_> = specs
+        .iter()
+        .flat_map(|s| &s.options)
+        .flat_map(|o| names(&o.names))
+        .collect();
+    value_options.sort_unstable();
+    value_options.dedup();
+
+    let mut res = format!(
+        "{fn_name}() {{\n    \
+            local cur=\"${{COMP_WORDS[COMP_CWORD]}}\"\n    \
+            local prev=\"${{COMP_WORDS[COMP_CWORD-1]}}\"\n"
+    );
+    // values of options are completed by the default completion
+    if !value_options.is_empty() {
+        res.push_str(&format!(
+            "    case \"$prev\" in\n        {}) return ;;\n    esac\n",
+            value_options.join("|")
+        ));
+    }
+    let words = |spec: &ArgSpec| {
+        let mut words = vec![];
+        for names_ in spec.options.iter().map(|o| &o.names) {
+            words.extend(names(names_));
+        }
+        for names_ in spec.flags.iter().map(|f| &f.names) {
+            words.extend(names(names_));
+        }
+        words.push("--help");
+        words.join(" ")
+    };
+    match interface {
+        Interface::Main(spec) => res.push_str(&format!(
+            "    if [[ \"$cur\" == -* ]]; then\n        \
+                COMPREPLY=($(compgen -W \"{}\" -- \"$cur\"))\n    \
+            fi\n",
+            words(spec)
+        )),
+        Interface::Modes(modes, specs) => {
+            let mode_names: Vec<_> = modes.modes.iter().map(|(n, _)| n.as_str()).collect();
+            res.push_str(&format!(
+                "    if [ \"$COMP_CWORD\" -eq 1 ]; then\n        \
+                    COMPREPLY=($(compgen -W \"{} --help\" -- \"$cur\"))\n        \
+                    return\n    \
+                fi\n    \
+                [[ \"$cur\" == -* ]] || return\n    \
+                case \"${{COMP_WORDS[1]}}\" in\n",
+                mode_names.join(" ")
+            ));
+            for (name, spec) in mode_names.iter().zip(specs) {
+                res.push_str(&format!(
+                    "        {}) COMPREPLY=($(compgen -W \"{}\" -- \"$cur\")) ;;\n",
+                    name,
+                    words(spec)
+                ));
+            }
+            res.push_str("    esac\n");
+        }
+    }
+    res.push_str(&format!(
+        "}}\ncomplete -o default -F {} {}\n",
+        fn_name, cmd_name
+    ));
+    res
+}
+
+fn zsh(cmd_name: &str, interface: &Interface) -> String {
+    let fn_name = format!("_levis_{}", identifier(cmd_name));
+    let arguments = |spec: &ArgSpec, indent: &str| {
+        let mut specs = vec![];
+        for o in &spec.options {
+            specs.push(zsh_arg_spec(&o.names, &o.help, false, ":value:"));
+        }
+        for f in &spec.flags {
+            specs.push(zsh_arg_spec(&f.names, &f.help, true, ""));
+        }
+        specs.push("'*:file:_files'".to_string());
+        specs.join(&format!(" \\\n{}", indent))
+    };
+    let mut res = format!("#compdef {}\n\n{}() {{\n", cmd_name, fn_name);
+    match interface {
+        Interface::Main(spec) => {
+            res.push_str(&format!(
+                "    _arguments -s \\\n        {}\n",
+                arguments(spec, "        ")
+            ));
+        }
+        Interface::Modes(modes, specs) => {
+            let values: Vec<_> = modes
+                .modes
+                .iter()
+                .map(|(name, help)| format!("'{}[{}]'", name, escape_zsh(help)))
+                .collect();
+            res.push_str(&format!(
+                "    local line state\n    \
+                _arguments -C '1: :->mode' '*::arg:->args'\n    \
+                case $state in\n        \
+                    mode) _values 'mode' {} ;;\n        \
+                    args)\n            \
+                        case $line[1] in\n",
+                values.join(" ")
+            ));
+            for ((name, _), spec) in modes.modes.iter().zip(specs) {
+                res.push_str(&format!(
+                    "                {}) _arguments -s \\\n                    {} ;;\n",
+                    name,
+                    arguments(spec, "                    ")
+                ));
+            }
+            res.push_str("            esac ;;\n    esac\n");
+        }
+    }
+    res.push_str(&format!("}}\n\ncompdef {} {}\n", fn_name, cmd_name));
+    res
+}
+
+/// e.g. `'(-v --verbose)'{-v,--verbose}'[talk more]'`. Flags are counted, so they can be repeated
+fn zsh_arg_spec(names_: &ArgNames, help: &Option<String>, repeatable: bool, value: &str) -> String {
+    let names_ = names(names_);
+    let help = format!("[{}]{}", escape_zsh(help), value);
+    match (names_.as_slice(), repeatable) {
+        ([name], false) => format!("'{}{}'", name, help),
+        ([name], true) => format!("'*{}{}'", name, help),
+        (names_, false) => format!("'({})'{{{}}}'{}'", names_.join(" "), names_.join(","), help),
+        (names_, true) => format!("'*'{{{}}}'{}'", names_.join(","), help),
+    }
+}
+
+fn fish(cmd_name: &str, interface: &Interface) -> String {
+    let mut res = String::new();
+    let mut add_spec = |spec: &ArgSpec, condition: Option<String>| {
+        let condition = condition
+            .map(|c| format!(" -n '{}'", c))
+            .unwrap_or_default();
+        let lines = spec
+            .options
+            .iter()
+            .map(|o| (&o.names, &o.help, " -r"))
+            .chain(spec.flags.iter().map(|f| (&f.names, &f.help, "")));
+        for (names_, help, requires_value) in lines {
+            res.push_str(&format!(
+                "complete -c {}{}{}{}{}\n",
+                cmd_name,
+                condition,
+                fish_names(names_),
+                requires_value,
+                fish_description(help)
+            ));
+        }
+    };
+    match interface {
+        Interface::Main(spec) => add_spec(spec, None),
+        Interface::Modes(modes, specs) => {
+            for ((name, _), spec) in modes.modes.iter().zip(specs) {
+                add_spec(spec, Some(format!("__fish_seen_subcommand_from {}", name)));
+            }
+            for (name, help) in &modes.modes {
+                res.push_str(&format!(
+                    "complete -c {} -n __fish_use_subcommand -f -a {}{}\n",
+                    cmd_name,
+                    name,
+                    fish_description(help)
+                ));
+            }
+        }
+    }
+    res
+}
+
+/// fish only accepts single letters after -s, longer short names are old style options
+fn fish_names(names: &ArgNames) -> String {
+    let mut res = String::new();
+    if let Some(short) = &names.short {
+        let short = short.trim_start_matches('-');
+        let kind = if short.chars().count() == 1 { "s" } else { "o" };
+        res.push_str(&format!(" -{} {}", kind, short));
+    }
+    if let Some(long) = &names.long {
+        res.push_str(&format!(" -l {}", long.trim_start_matches('-')));
+    }
+    res
+}
+
+fn fish_description(help: &Option<String>) -> String {
+    match first_line(help) {
+        "" => String::new(),
+        line => format!(" -d '{}'", line.replace('\'', "\\'")),
+    }
+}
+
+fn names(names: &ArgNames) -> Vec<&str> {
+    names
+        .short
+        .iter()
+        .chain(names.long.iter())
+        .map(String::as_str)
+        .collect()
+}
+
+fn first_line(help: &Option<String>) -> &str {
+    help.as_deref()
+        .and_then(|h| h.lines().next())
+        .unwrap_or_default()
+}
+
+/// the descriptions are inside single quotes and brackets
+fn escape_zsh(help: &Option<String>) -> String {
+    first_line(help)
+        .replace('\'', "'\\''")
+        .replace('[', "\\[")
+        .replace(']', "\\]")
+        .replace(':', "\\:")
+}
+
+/// the name of the script might contain characters that aren't allowed in function names
+fn identifier(cmd_name: &str) -> String {
+    cmd_name
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect()
+}
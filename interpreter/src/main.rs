@@ -1,4 +1,4 @@
-use anyhow::{Context, Result};
+use anyhow::{anyhow, Context, Result};
 use clap::Parser;
 
 use leviscript_lib::compiler::Compilable;
@@ -8,6 +8,7 @@ use leviscript_lib::{core::*, parser, vm};
 
 use std::path::PathBuf;
 
+mod completions;
 #[cfg(feature = "dev")]
 mod debugger;
 
@@ -16,6 +17,10 @@ mod debugger;
 struct Cli {
     script: PathBuf,
 
+    /// print completions for the arguments the script declares, instead of running it
+    #[arg(long, value_enum)]
+    completions: Option<completions::Shell>,
+
     #[cfg(feature = "dev")]
     #[arg(short = 'p', long)]
     show_parse_tree: bool,
@@ -35,7 +40,15 @@ struct Cli {
 
 fn main() -> Result<()> {
     // let src = std::fs::read_to_string("../test-script/xexp.les")?;
-    let cli = Cli::parse();
+    // like paths, arguments that aren't valid UTF-8 are rejected instead of being changed
+    let args = std::env::args_os()
+        .map(|a| {
+            a.into_string()
+                .map_err(|a| anyhow!("Argument is not valid UTF-8: {}", a.to_string_lossy()))
+        })
+        .collect::<Result<_>>()?;
+    let (own_args, script_args) = split_args(args);
+    let cli = Cli::parse_from(own_args);
     let file_name = cli.script.display().to_string();
     let src = std::fs::read_to_string(&cli.script).context(format!(
         "loading script: {}\ncwd:{}",
@@ -51,6 +64,13 @@ fn main() -> Result<()> {
     }
 
     let (ast, spans) = parser::to_ast(parse_tree)?;
+    if let Some(shell) = cli.completions {
+        let interface = completions::find_interface(&ast)
+            .ok_or_else(|| anyhow!("{} declares no main or mode sections", file_name))?;
+        let cmd_name = cli.script.file_name().unwrap().to_string_lossy();
+        print!("{}", completions::generate(shell, &cmd_name, &interface));
+        return Ok(());
+    }
     #[cfg(feature = "dev")]
     if cli.show_ast {
         println!("{:#?}", ast);
@@ -62,7 +82,12 @@ fn main() -> Result<()> {
         Ok(x) => x,
         Err(e) => exit_with_error(&e.to_string(), e.get_ast_id(), &spans, &file_name),
     };
-    let compilation_result = ast.compile(ByteCodeBuilder::default(), &type_index);
+    let builder = ByteCodeBuilder {
+        file: file_name.clone(),
+        lines: spans.iter().map(|s| s.start_pos().line_col().0).collect(),
+        ..Default::default()
+    };
+    let compilation_result = ast.compile(builder, &type_index);
     match compilation_result {
         Ok(builder) => {
             #[cfg(feature = "dev")]
@@ -87,7 +112,8 @@ fn main() -> Result<()> {
                 return res;
             }
 
-            let res = match run(final_bc, &spans, &debug_info) {
+            let args = std::iter::once(file_name).chain(script_args).collect();
+            let res = match run(final_bc, args, &spans, &debug_info) {
                 Ok(res) => res,
                 Err(e) => {
                     eprintln!("{}", e);
@@ -102,14 +128,41 @@ fn main() -> Result<()> {
     }
 }
 
-pub fn run(bc: ByteCode, spans: &[Span], dinfo: &DebugInformation) -> Result<i32, String> {
+/// Everything after the script belongs to the script. Clap would still interpret `--help` and the
+/// like there, so the args are split before clap sees them
+fn split_args(mut args: Vec<String>) -> (Vec<String>, Vec<String>) {
+    // the own options that take a value as a separate argument
+    const WITH_VALUE: [&str; 1] = ["--completions"];
+    let mut script_idx = 1;
+    while script_idx < args.len() && args[script_idx].starts_with('-') {
+        if WITH_VALUE.contains(&args[script_idx].as_str()) {
+            script_idx += 1;
+        }
+        script_idx += 1;
+    }
+    let script_args = args.split_off((script_idx + 1).min(args.len()));
+    (args, script_args)
+}
+
+pub fn run(
+    bc: ByteCode,
+    args: Vec<String>,
+    spans: &[Span],
+    dinfo: &DebugInformation,
+) -> Result<i32, String> {
     let mut runner = Runner::new(bc);
+    runner.mem.argv = args.clone();
+    runner.mem.args = args;
     runner.reset_pc();
+    vm::signals::install();
 
     loop {
         match unsafe { runner.step() } {
             StepResult::Ok => {}
             StepResult::Done(res) => return Ok(res),
+            // the runner is dropped on return, which cleans up like after an error. Then the exit
+            // code is the one of a shell for a program stopped by the signal
+            StepResult::Err(vm::Error::Interrupted(sig)) => return Ok(128 + sig),
             StepResult::Err(vm::Error::Runtime(msg)) => {
                 let ast_id = runner.pc_to_ast_id(dinfo);
 
@@ -6,7 +6,7 @@ use syn::{parse_macro_input, ItemEnum};
 pub fn opcode_impl(tokens: TokenStream) -> TokenStream {
     let input = parse_macro_input!(tokens as ItemEnum);
     let variants: Vec<_> = input.variants.iter().collect();
-    assert!(variants.len() <= 2 ^ 16, "Too many variants");
+    assert!(variants.len() <= 1 << 16, "Too many variants");
     let enum_name = input.ident;
 
     let variant_names = variants.iter().map(|v| &v.ident);
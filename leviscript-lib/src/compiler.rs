@@ -35,6 +35,24 @@ pub enum CompilationError {
         actual: DataType,
         expected: TypeSet,
     },
+
+    #[error("Only lists can be spliced, found {actual:?}")]
+    NotSpliceable { ast_id: usize, actual: DataType },
+
+    #[error("Splicing is only possible in the arguments of a call")]
+    SpliceOutsideOfCall { ast_id: usize },
+
+    #[error("Only one list can be spliced into the arguments of a non-variadic function")]
+    AmbiguousSplice { ast_id: usize },
+
+    #[error("Invalid regex: {msg}")]
+    InvalidRegex { ast_id: usize, msg: String },
+
+    #[error("Fn fragments can only be bound with let, or passed to built ins that take a function, like map")]
+    FragmentOutsideOfCall { ast_id: usize },
+
+    #[error("Expected a fn fragment, e.g. (fn x -> ...)")]
+    FragmentExpected { ast_id: usize },
 }
 
 impl CompilationError {
@@ -44,6 +62,12 @@ impl CompilationError {
             Self::CompilerBug { ast_id, .. } => *ast_id,
             Self::ArgCount { ast_id, .. } => *ast_id,
             Self::TypeError { ast_id, .. } => *ast_id,
+            Self::NotSpliceable { ast_id, .. } => *ast_id,
+            Self::SpliceOutsideOfCall { ast_id } => *ast_id,
+            Self::AmbiguousSplice { ast_id } => *ast_id,
+            Self::InvalidRegex { ast_id, .. } => *ast_id,
+            Self::FragmentOutsideOfCall { ast_id } => *ast_id,
+            Self::FragmentExpected { ast_id } => *ast_id,
         }
     }
 }
@@ -70,12 +94,8 @@ macro_rules! compiler_bug {
 }
 
 impl Compilable for FnFragment {
-    fn compile(
-        &self,
-        mut builder: ByteCodeBuilder,
-        expr_types: &TypeIndex,
-    ) -> Result<ByteCodeBuilder> {
-        todo!();
+    fn compile(&self, _: ByteCodeBuilder, _: &TypeIndex) -> Result<ByteCodeBuilder> {
+        Err(CompilationError::FragmentOutsideOfCall { ast_id: self.id })
     }
 }
 
@@ -97,6 +117,15 @@ impl Compilable for Call {
                 };
         let opcode =
             vm::built_ins::opcode(callee_name).expect(&format!("invalid builtin: {}", callee_name));
+        if vm::built_ins::is_scoped(callee_name) {
+            return compile_scoped_call(self, callee_sign, opcode, builder, expr_types);
+        }
+        if callee_sign.takes_fragment() {
+            return compile_higher_order_call(self, callee_sign, opcode, builder, expr_types);
+        }
+        if args.iter().any(|a| matches!(a, Expr::Splice(_))) {
+            return compile_spliced_call(self, callee_sign, opcode, builder, expr_types);
+        }
         let old_builder = builder.clone();
         builder.open_scope(*id);
         for (a, t) in args.iter().zip(&callee_sign.args) {
@@ -136,13 +165,466 @@ impl Compilable for Call {
         if let Some(n) = n_var_args {
             builder.push_primitive_to_stack(n.into(), *id);
         }
-        builder.text.push_back(opcode);
+        builder.push_opcode(opcode, *id);
         builder.create_value_in_memory(&callee_sign.result.concrete_type().unwrap(), *id);
         builder.collapse_scope();
         Ok(builder)
     }
 }
 
+/// Compiles a call in which at least one argument is spliced.
+///
+/// How many values end up on the stack is only known at runtime. So all arguments are evaluated
+/// into temporaries first, and are then copied or spread into a region above them, which the
+/// stack_info doesn't know about. After the call that region is truncated, and the scope of the
+/// temporaries is collapsed as usual.
+fn compile_spliced_call(
+    call: &Call,
+    callee_sign: &Signature,
+    opcode: OpCode,
+    mut builder: ByteCodeBuilder,
+    expr_types: &TypeIndex,
+) -> Result<ByteCodeBuilder> {
+    let Call { id, args, .. } = call;
+    let n_fixed = callee_sign.args.len();
+    let is_variadic = callee_sign.var_arg.is_some();
+    if !is_variadic
+        && args
+            .iter()
+            .filter(|a| matches!(a, Expr::Splice(_)))
+            .nth(1)
+            .is_some()
+    {
+        return Err(CompilationError::AmbiguousSplice { ast_id: *id });
+    }
+    if !is_variadic && args.len() - 1 > n_fixed {
+        return Err(CompilationError::ArgCount {
+            ast_id: *id,
+            found: args
+                .iter()
+                .map(|a| expr_types.get(&a.get_id()).unwrap().clone())
+                .collect(),
+            expected: callee_sign.clone(),
+        });
+    }
+
+    builder.open_scope(*id);
+    // the stack index of each temporary, and how it's spread, if it is
+    let mut temps = vec![];
+    // the next fixed parameter to fill
+    let mut param_idx = 0;
+    for (i, arg) in args.iter().enumerate() {
+        if let Expr::Splice(Splice {
+            id: splice_id,
+            list,
+        }) = arg
+        {
+            builder = list.compile(builder, expr_types)?;
+            let list_type = expr_types.get(&list.get_id()).unwrap();
+            let DataType::HeapType(HeapType::Vec(elem_type)) = list_type else {
+                return Err(CompilationError::NotSpliceable {
+                    ast_id: *splice_id,
+                    actual: list_type.clone(),
+                });
+            };
+            // A splice fills all remaining fixed parameters. For non-variadic functions the args
+            // after it are subtracted, and the list must fit exactly
+            let covered = if is_variadic {
+                n_fixed.saturating_sub(param_idx)
+            } else {
+                n_fixed - param_idx - (args.len() - i - 1)
+            };
+            let mut elem_targets: Vec<_> = callee_sign.args[param_idx..param_idx + covered]
+                .iter()
+                .collect();
+            elem_targets.extend(&callee_sign.var_arg);
+            if let Some(t) = elem_targets
+                .into_iter()
+                .find(|t| !t.is_sattisfied_by(elem_type))
+            {
+                return Err(CompilationError::TypeError {
+                    ast_id: *splice_id,
+                    actual: elem_type.as_ref().clone(),
+                    expected: t.clone(),
+                });
+            }
+            param_idx += covered;
+            let info = SpreadInfo {
+                stack_idx: builder.stack_info.len() - 1,
+                min_len: covered,
+                exact: !is_variadic,
+            };
+            temps.push((info.stack_idx, Some(info)));
+        } else {
+            builder = arg.compile(builder, expr_types)?;
+            let t = if param_idx < n_fixed {
+                param_idx += 1;
+                &callee_sign.args[param_idx - 1]
+            } else {
+                callee_sign.var_arg.as_ref().unwrap()
+            };
+            if !builder.check_and_fix_type_of_stack_top(t) {
+                return Err(CompilationError::TypeError {
+                    ast_id: arg.get_id().into(),
+                    actual: expr_types.get(&arg.get_id()).unwrap().clone(),
+                    expected: t.clone(),
+                });
+            }
+            temps.push((builder.stack_info.len() - 1, None));
+        }
+    }
+
+    let region_start = builder.stack_info.len();
+    for (stack_idx, spread) in temps {
+        match spread {
+            Some(info) => builder.push_opcode(OpCode::SpreadVec(info), *id),
+            None => builder.push_opcode(OpCode::RepushStackEntry(stack_idx), *id),
+        }
+    }
+    if is_variadic {
+        builder.push_opcode(OpCode::PushVarArgCount(region_start + n_fixed), *id);
+    }
+    builder.push_opcode(opcode, *id);
+    builder.push_opcode(OpCode::StackTopToReg(0), *id);
+    builder.push_opcode(OpCode::TruncateStack(region_start), *id);
+    builder.push_opcode(OpCode::ReadReg(0), *id);
+    builder.create_value_in_memory(callee_sign.result.concrete_type().unwrap(), *id);
+    builder.collapse_scope();
+    Ok(builder)
+}
+
+/// Compiles a call of a scoped built in, see [vm::built_ins::is_scoped].
+///
+/// The result of the built in stays on the stack below the body, a fragment's argument is bound
+/// to it. After the body, the change is undone, and the scope is collapsed to the body's value.
+fn compile_scoped_call(
+    call: &Call,
+    callee_sign: &Signature,
+    opcode: OpCode,
+    mut builder: ByteCodeBuilder,
+    expr_types: &TypeIndex,
+) -> Result<ByteCodeBuilder> {
+    let Call { id, args, .. } = call;
+    let Some((body, setup_args)) = args.split_last() else {
+        compiler_bug!(*id, "scoped call without body");
+    };
+    if setup_args.len() != callee_sign.args.len() {
+        return Err(CompilationError::ArgCount {
+            ast_id: *id,
+            found: setup_args
+                .iter()
+                .map(|a| expr_types.get(&a.get_id()).unwrap().clone())
+                .collect(),
+            expected: callee_sign.clone(),
+        });
+    }
+    builder.open_scope(*id);
+    for (a, t) in setup_args.iter().zip(&callee_sign.args) {
+        builder = a.compile(builder, expr_types)?;
+        if !builder.check_and_fix_type_of_stack_top(t) {
+            return Err(CompilationError::TypeError {
+                ast_id: a.get_id().into(),
+                actual: expr_types.get(&a.get_id()).unwrap().clone(),
+                expected: t.clone(),
+            });
+        }
+    }
+    builder.push_opcode(opcode, *id);
+    builder.create_value_in_memory(callee_sign.result.concrete_type().unwrap(), *id);
+    match body {
+        Expr::FnFragment(fragment) if fragment.args.len() == 1 => {
+            builder.add_symbol_for_stack_top(&fragment.args[0].name);
+            builder = fragment.body.compile(builder, expr_types)?;
+        }
+        body => builder = body.compile(builder, expr_types)?,
+    }
+    if opcode == OpCode::Measure {
+        builder.push_opcode(OpCode::LeaveMeasure, *id);
+        builder.create_value_in_memory(
+            expr_types.get(&EnvironmentIdentifier::AstId(*id)).unwrap(),
+            *id,
+        );
+    } else {
+        builder.push_opcode(OpCode::LeaveScope, *id);
+    }
+    builder.collapse_scope();
+    Ok(builder)
+}
+
+/// Compiles a call of a higher order built in, like map, into a loop, see [vm::iteration].
+///
+/// The arguments that aren't functions are evaluated first, the list is the last one of them.
+/// The body of the fragment is the body of the loop, its arguments are bound to what
+/// [OpCode::IterNext] pushes. The signature is the instantiated one, so all types are known.
+fn compile_higher_order_call(
+    call: &Call,
+    callee_sign: &Signature,
+    opcode: OpCode,
+    mut builder: ByteCodeBuilder,
+    expr_types: &TypeIndex,
+) -> Result<ByteCodeBuilder> {
+    let Call { id, args, .. } = call;
+    if args.len() != callee_sign.args.len() {
+        return Err(CompilationError::ArgCount {
+            ast_id: *id,
+            found: args
+                .iter()
+                .map(|a| expr_types.get(&a.get_id()).unwrap().clone())
+                .collect(),
+            expected: callee_sign.clone(),
+        });
+    }
+    builder.open_scope(*id);
+    let mut passed_fragment = None;
+    for (a, t) in args.iter().zip(&callee_sign.args) {
+        if let Some(DataType::Callable(_, fragment_sign)) = t.concrete_type() {
+            let fragment = match a {
+                Expr::FnFragment(fragment) => (fragment.clone(), None),
+                Expr::Symbol(Symbol(_, name)) => match builder.bound_fragment(name) {
+                    Some((fragment, sign, symbols)) if sign.args == fragment_sign.args => {
+                        (fragment, Some(symbols))
+                    }
+                    Some((_, sign, _)) => {
+                        return Err(CompilationError::TypeError {
+                            ast_id: a.get_id().into(),
+                            actual: DataType::fragment(sign),
+                            expected: t.clone(),
+                        })
+                    }
+                    None => {
+                        return Err(CompilationError::FragmentExpected {
+                            ast_id: a.get_id().into(),
+                        })
+                    }
+                },
+                _ => {
+                    return Err(CompilationError::FragmentExpected {
+                        ast_id: a.get_id().into(),
+                    })
+                }
+            };
+            passed_fragment = Some((fragment, fragment_sign));
+            continue;
+        }
+        builder = a.compile(builder, expr_types)?;
+        if !builder.check_and_fix_type_of_stack_top(t) {
+            return Err(CompilationError::TypeError {
+                ast_id: a.get_id().into(),
+                actual: expr_types.get(&a.get_id()).unwrap().clone(),
+                expected: t.clone(),
+            });
+        }
+    }
+    let Some(((fragment, definition_symbols), fragment_sign)) = passed_fragment else {
+        compiler_bug!(*id, "higher order call without a function");
+    };
+    let result_type = callee_sign.result.concrete_type().unwrap();
+    if let (Expr::Symbol(Symbol(_, name)), DataType::HeapType(HeapType::Seq(_))) =
+        (call.callee.as_ref(), result_type)
+    {
+        let Some(kind) = vm::built_ins::lazy_kind(name) else {
+            compiler_bug!(*id, "{} can't make a lazy sequence", name);
+        };
+        builder = compile_lazy_fragment(
+            *id,
+            kind,
+            &fragment,
+            definition_symbols,
+            fragment_sign,
+            builder,
+            expr_types,
+        )?;
+        builder.create_value_in_memory(result_type, *id);
+        builder.collapse_scope();
+        return Ok(builder);
+    }
+    builder.push_opcode(opcode, *id);
+
+    let loop_start = builder.text.len();
+    builder.push_opcode(OpCode::IterNext(0), *id);
+    // a fragment bound with let refers to the symbols where it was defined
+    let call_site_symbols =
+        definition_symbols.map(|symbols| std::mem::replace(&mut builder.symbol_table, symbols));
+    builder.open_scope(fragment.id);
+    for (arg, t) in fragment.args.iter().zip(&fragment_sign.args) {
+        builder.create_value_in_memory(t.concrete_type().unwrap(), arg.id);
+        builder.add_symbol_for_stack_top(&arg.name);
+    }
+    builder = fragment.body.compile(builder, expr_types)?;
+    if !builder.check_and_fix_type_of_stack_top(&fragment_sign.result) {
+        return Err(CompilationError::TypeError {
+            ast_id: fragment.body.get_id().into(),
+            actual: expr_types.get(&fragment.body.get_id()).unwrap().clone(),
+            expected: fragment_sign.result.clone(),
+        });
+    }
+    builder.push_opcode(OpCode::IterCollect, *id);
+    // the result and the arguments are dropped, the iteration keeps what it needs
+    builder.collapse_scope();
+    builder.pop_stack_entries(1);
+    if let Some(symbols) = call_site_symbols {
+        builder.symbol_table = symbols;
+    }
+    let jump_back = builder.text.len();
+    builder.push_opcode(OpCode::Jump(0), *id);
+    builder.set_jump_target(jump_back, loop_start);
+    builder.set_jump_target(loop_start, builder.text.len());
+
+    builder.push_opcode(OpCode::IterEnd, *id);
+    builder.create_value_in_memory(callee_sign.result.concrete_type().unwrap(), *id);
+    builder.collapse_scope();
+    Ok(builder)
+}
+
+/// The fragment of a lazy sequence, see [vm::sequence::Fragment]. The values of the symbols it
+/// refers to are pushed for the sequence to copy, then the code of the fragment is jumped over
+fn compile_lazy_fragment(
+    id: usize,
+    kind: vm::IterKind,
+    fragment: &FnFragment,
+    definition_symbols: Option<Scopes<String, usize>>,
+    fragment_sign: &Signature,
+    mut builder: ByteCodeBuilder,
+    expr_types: &TypeIndex,
+) -> Result<ByteCodeBuilder> {
+    let symbols = definition_symbols.unwrap_or_else(|| builder.symbol_table.clone());
+    let mut captured: Vec<(String, usize)> = vec![];
+    for node in fragment.body.iter() {
+        let AstNodeRef::Symbol(Symbol(_, name)) = node else {
+            continue;
+        };
+        let Some(&idx) = symbols.find_entry(name) else {
+            continue;
+        };
+        // bound fragments aren't values, they can't be copied
+        let is_value = matches!(
+            builder.stack_info[idx].type_info,
+            DataTypeInfo::StackType(_)
+                | DataTypeInfo::HeapTypeInfo { .. }
+                | DataTypeInfo::DataSecTypeInfo { .. }
+        );
+        if is_value && !captured.iter().any(|(n, _)| n == name) {
+            captured.push((name.clone(), idx));
+        }
+    }
+    for (_, idx) in &captured {
+        builder.repush_stack_entry(*idx, id);
+    }
+    let jump = builder.text.len();
+    builder.push_opcode(OpCode::Jump(0), id);
+
+    let fragment_start = builder.text.len();
+    let outer = builder.enter_frame();
+    builder.open_scope(fragment.id);
+    for (name, idx) in &captured {
+        let t = outer.stack_info[*idx].type_info.clone().into_datatype();
+        builder.create_value_in_memory(&t, id);
+        builder.add_symbol_for_stack_top(name);
+    }
+    for (arg, t) in fragment.args.iter().zip(&fragment_sign.args) {
+        builder.create_value_in_memory(t.concrete_type().unwrap(), arg.id);
+        builder.add_symbol_for_stack_top(&arg.name);
+    }
+    builder = fragment.body.compile(builder, expr_types)?;
+    if !builder.check_and_fix_type_of_stack_top(&fragment_sign.result) {
+        return Err(CompilationError::TypeError {
+            ast_id: fragment.body.get_id().into(),
+            actual: expr_types.get(&fragment.body.get_id()).unwrap().clone(),
+            expected: fragment_sign.result.clone(),
+        });
+    }
+    // only the result is left, the sequence takes it
+    builder.collapse_scope();
+    builder.push_opcode(OpCode::FragmentEnd, id);
+    builder.leave_frame(outer);
+    builder.set_jump_target(jump, builder.text.len());
+
+    let lazy = builder.text.len();
+    let info = LazyInfo {
+        kind,
+        captured: captured.len(),
+        offset: 0,
+    };
+    builder.push_opcode(OpCode::Lazy(info), id);
+    builder.set_jump_target(lazy, fragment_start);
+    Ok(builder)
+}
+
+impl Compilable for Main {
+    fn compile(
+        &self,
+        mut builder: ByteCodeBuilder,
+        expr_types: &TypeIndex,
+    ) -> Result<ByteCodeBuilder> {
+        let Main {
+            id,
+            spec,
+            bindings,
+            body,
+        } = self;
+        // the defaults are casted at runtime, like the arguments, but they are known already
+        for (option, binding) in spec.options.iter().zip(bindings) {
+            if let (ArgType::Int, Some(default)) = (option.arg_type, &option.default) {
+                if vm::args::parse_int(default).is_none() {
+                    return Err(CompilationError::TypeError {
+                        ast_id: binding.id,
+                        actual: DataType::str(),
+                        expected: DataType::int().into(),
+                    });
+                }
+            }
+        }
+
+        builder.open_scope(*id);
+        builder.arg_specs.push_back(spec.clone());
+        builder.push_opcode(OpCode::ParseArgs(builder.arg_specs.len() - 1), *id);
+        for (binding, (name, t)) in bindings.iter().zip(spec.bindings()) {
+            builder.create_value_in_memory(&t, binding.id);
+            builder.add_symbol_for_stack_top(&name);
+        }
+        builder = body.compile(builder, expr_types)?;
+        builder.collapse_scope();
+        Ok(builder)
+    }
+}
+
+impl Compilable for Modes {
+    fn compile(
+        &self,
+        mut builder: ByteCodeBuilder,
+        expr_types: &TypeIndex,
+    ) -> Result<ByteCodeBuilder> {
+        let Modes { id, spec, modes } = self;
+        builder.mode_specs.push_back(spec.clone());
+        builder.push_opcode(OpCode::SelectMode(builder.mode_specs.len() - 1), *id);
+        let mut jumps_to_end = vec![];
+        for (i, mode) in modes.iter().enumerate() {
+            let skip_mode = builder.text.len();
+            builder.push_opcode(
+                OpCode::JumpUnlessMode(ModeJump { mode: i, offset: 0 }),
+                mode.id,
+            );
+            builder = mode.compile(builder, expr_types)?;
+            // the modes have different result types, so the result is dropped
+            builder.pop_stack_entries(1);
+            jumps_to_end.push(builder.text.len());
+            builder.push_opcode(OpCode::Jump(0), mode.id);
+            builder.set_jump_target(skip_mode, builder.text.len());
+        }
+        for jump in jumps_to_end {
+            builder.set_jump_target(jump, builder.text.len());
+        }
+        builder.push_primitive_to_stack(CopyValue::Unit, *id);
+        Ok(builder)
+    }
+}
+
+impl Compilable for Splice {
+    fn compile(&self, _: ByteCodeBuilder, _: &TypeIndex) -> Result<ByteCodeBuilder> {
+        Err(CompilationError::SpliceOutsideOfCall { ast_id: self.id })
+    }
+}
+
 impl Compilable for Let {
     fn compile(
         &self,
@@ -156,7 +638,14 @@ impl Compilable for Let {
         } = self;
 
         let old_builder = builder.clone();
-        builder = value_expr.compile(builder, expr_types)?;
+        if let Expr::FnFragment(fragment) = value_expr.as_ref() {
+            let Some(DataType::Callable(_, sign)) = expr_types.get(&fragment.get_id()) else {
+                compiler_bug!(fragment.id, "fragment without a signature");
+            };
+            builder.push_fragment(fragment, sign.as_ref().clone());
+        } else {
+            builder = value_expr.compile(builder, expr_types)?;
+        }
         assert_stack_grew_by_one(*id, &old_builder, &builder);
         builder.add_symbol_for_stack_top(symbol_name);
         Ok(builder)
@@ -166,7 +655,25 @@ impl Compilable for Let {
 impl Compilable for StrLit {
     fn compile(&self, mut builder: ByteCodeBuilder, _: &TypeIndex) -> Result<ByteCodeBuilder> {
         let StrLit(id, val) = self;
-        builder.add_to_datasection_and_push_ref(Value::Str(val.clone()), *id);
+        builder.add_to_datasection_and_push_ref(Value::Str(val.clone()), HeapType::Str, *id);
+        Ok(builder)
+    }
+}
+
+impl Compilable for RegexLit {
+    fn compile(&self, mut builder: ByteCodeBuilder, _: &TypeIndex) -> Result<ByteCodeBuilder> {
+        let RegexLit(id, pattern) = self;
+        if let Err(e) = regex::Regex::new(pattern) {
+            return Err(CompilationError::InvalidRegex {
+                ast_id: *id,
+                msg: e.to_string(),
+            });
+        }
+        builder.add_to_datasection_and_push_ref(
+            Value::Regex(pattern.clone()),
+            HeapType::Regex,
+            *id,
+        );
         Ok(builder)
     }
 }
@@ -178,7 +685,32 @@ impl Compilable for Symbol {
         expr_types: &TypeIndex,
     ) -> Result<ByteCodeBuilder> {
         let Symbol(ast_id, name) = self;
-        map_to_symbol_not_found(builder.copy_symbol_target_to_stack_top(name), *ast_id, name)?;
+        // globals can be shadowed
+        if builder.symbol_table.find_entry(name).is_none() {
+            if name == "FILE" {
+                let file = builder.file.clone();
+                builder.add_to_datasection_and_push_ref(Value::Str(file), HeapType::Str, *ast_id);
+                return Ok(builder);
+            }
+            if name == "LINE" {
+                let line = builder.lines.get(*ast_id).copied().unwrap_or_default();
+                builder.push_primitive_to_stack(CopyValue::Int(line as i64), *ast_id);
+                return Ok(builder);
+            }
+            if let Some(global) = vm::global(name) {
+                builder.push_opcode(OpCode::PushGlobal(global), *ast_id);
+                builder.create_value_in_memory(&vm::global_type(name).unwrap(), *ast_id);
+                return Ok(builder);
+            }
+        }
+        if builder.bound_fragment(name).is_some() {
+            return Err(CompilationError::FragmentOutsideOfCall { ast_id: *ast_id });
+        }
+        map_to_symbol_not_found(
+            builder.copy_symbol_target_to_stack_top(name, *ast_id),
+            *ast_id,
+            name,
+        )?;
         Ok(builder)
     }
 }
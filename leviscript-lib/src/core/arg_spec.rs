@@ -0,0 +1,125 @@
+//! The declaration of the command line arguments of a script, as written in the main section.
+//!
+//! The spec is put into the byte code as it is, and the vm parses argv according to it, see
+//! [crate::vm::args]
+
+use serde::{Deserialize, Serialize};
+
+use super::DataType;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ArgSpec {
+    /// The first paragraph is the short help, the whole string the long help
+    pub help: Option<String>,
+    pub options: Vec<OptionSpec>,
+    pub flags: Vec<FlagSpec>,
+    pub positionals: Positionals,
+}
+
+/// The modes of a script. The first argument selects the mode, each mode has its own [ArgSpec],
+/// which already contains the shared options and flags
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ModesSpec {
+    /// The help of the all section
+    pub help: Option<String>,
+    /// shared by all modes, they are only needed for the help
+    pub options: Vec<OptionSpec>,
+    pub flags: Vec<FlagSpec>,
+    /// the name of each mode, and the first paragraph of its help
+    pub modes: Vec<(String, Option<String>)>,
+}
+
+/// An option takes a value, which is casted to the declared type
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OptionSpec {
+    pub names: ArgNames,
+    pub arg_type: ArgType,
+    /// Options without default are required
+    pub default: Option<String>,
+    pub help: Option<String>,
+}
+
+/// A flag takes no value, it evaluates to the number of its occurrences
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FlagSpec {
+    pub names: ArgNames,
+    pub help: Option<String>,
+}
+
+/// The names are stored with their leading dashes. At least one of them is set
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ArgNames {
+    pub short: Option<String>,
+    pub long: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum ArgType {
+    Str,
+    Int,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Positionals {
+    /// all positional arguments are bound to this name as a list
+    List(String),
+    /// exactly one positional argument per name. If there is a rest element, it is the index of
+    /// the name that takes the arguments that are left over as a list
+    Names {
+        names: Vec<String>,
+        rest: Option<usize>,
+    },
+}
+
+impl ArgSpec {
+    /// The names and types of the variables that the parsed arguments are bound to. The vm pushes
+    /// the values in the same order
+    pub fn bindings(&self) -> Vec<(String, DataType)> {
+        let options = self
+            .options
+            .iter()
+            .map(|o| (o.names.var_name(), o.arg_type.into()));
+        let flags = self
+            .flags
+            .iter()
+            .map(|f| (f.names.var_name(), DataType::int()));
+        let positionals: Vec<_> = match &self.positionals {
+            Positionals::List(name) => vec![(name.clone(), DataType::str().vec())],
+            Positionals::Names { names, rest } => names
+                .iter()
+                .enumerate()
+                .map(|(i, n)| {
+                    let dtype = if Some(i) == *rest {
+                        DataType::str().vec()
+                    } else {
+                        DataType::str()
+                    };
+                    (n.clone(), dtype)
+                })
+                .collect(),
+        };
+        options.chain(flags).chain(positionals).collect()
+    }
+}
+
+impl ArgNames {
+    /// The long name is preferred. Leading dashes are stripped, the remaining ones are converted
+    /// to underscores
+    pub fn var_name(&self) -> String {
+        let name = self.long.as_ref().or(self.short.as_ref()).unwrap();
+        name.trim_start_matches('-').replace('-', "_")
+    }
+
+    pub fn matches(&self, arg: &str) -> bool {
+        self.short.as_deref() == Some(arg) || self.long.as_deref() == Some(arg)
+    }
+}
+
+impl From<ArgType> for DataType {
+    fn from(value: ArgType) -> Self {
+        match value {
+            ArgType::Str => DataType::str(),
+            ArgType::Int => DataType::int(),
+        }
+    }
+}
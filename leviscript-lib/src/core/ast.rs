@@ -7,6 +7,7 @@ use std::ops::Deref;
 use serde::{Deserialize, Serialize};
 
 use crate::core::ast_macros::{define_ast_node_ref, mk_enum_node};
+use crate::core::{ArgSpec, ModesSpec};
 // this is needed because the mk_enum_node macro generates an implementation for it
 use crate::type_inference::EnvironmentIdentifier;
 
@@ -26,11 +27,15 @@ pub trait AstNode {
 #[derive(Debug, Serialize, Deserialize, Clone, AstNode)]
 pub struct Block(pub usize, #[children] pub Vec<Phrase>);
 
-mk_enum_node! { Phrase, Expr }
+mk_enum_node! { Phrase, Expr, Main, Modes }
 
 #[derive(Debug, Serialize, Deserialize, Clone, AstNode)]
 pub struct StrLit(pub usize, pub String);
 
+/// `r"pattern"`, the pattern is checked during compilation
+#[derive(Debug, Serialize, Deserialize, Clone, AstNode)]
+pub struct RegexLit(pub usize, pub String);
+
 #[derive(Debug, Serialize, Deserialize, Clone, AstNode)]
 pub struct Symbol(pub usize, pub String);
 
@@ -70,16 +75,52 @@ pub struct FnFragment {
     pub body: Box<Expr>,
 }
 
+/// `*expr` in the arguments of a call. The elements of the list are passed as separate arguments
+#[derive(Debug, Serialize, Deserialize, Clone, AstNode)]
+pub struct Splice {
+    pub id: usize,
+
+    #[child]
+    pub list: Box<Expr>,
+}
+
+/// The main section of a script. The arguments of the script are parsed according to the spec,
+/// and the body is evaluated with the values bound to the variables in bindings, which are in
+/// the order of [ArgSpec::bindings]
+#[derive(Debug, Serialize, Deserialize, Clone, AstNode)]
+pub struct Main {
+    pub id: usize,
+    pub spec: ArgSpec,
+
+    #[children]
+    pub bindings: Vec<ArgDef>,
+
+    #[child]
+    pub body: Box<Expr>,
+}
+
+/// The all and mode sections of a script. Each mode is compiled like a main section, and only
+/// the selected one is run
+#[derive(Debug, Serialize, Deserialize, Clone, AstNode)]
+pub struct Modes {
+    pub id: usize,
+    pub spec: ModesSpec,
+
+    #[children]
+    pub modes: Vec<Main>,
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone, AstNode)]
 pub struct ArgDef {
     pub id: usize,
     pub name: String,
 }
 
-mk_enum_node! { Expr, StrLit, Symbol, IntLit, Let, Call, FnFragment, Block}
+mk_enum_node! { Expr, StrLit, RegexLit, Symbol, IntLit, Let, Call, FnFragment, Block, Splice}
 
 define_ast_node_ref! {
-    Block, Phrase, StrLit, Symbol, IntLit, Let, Call, FnFragment, Expr, ArgDef,
+    Block, Phrase, StrLit, RegexLit, Symbol, IntLit, Let, Call, FnFragment, Expr, ArgDef, Splice,
+    Main, Modes,
 }
 
 impl<'a> AstNodeRef<'a> {
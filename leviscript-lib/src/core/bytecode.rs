@@ -10,6 +10,10 @@ use crate::vm;
 pub struct ByteCode {
     pub text: Vec<u8>,
     pub data: Vec<ComptimeValue>,
+    /// the main sections, which are referenced by [OpCode::ParseArgs]
+    pub arg_specs: Vec<ArgSpec>,
+    /// referenced by [OpCode::SelectMode]
+    pub mode_specs: Vec<ModesSpec>,
 }
 
 /// the header of the final bytecode
@@ -41,10 +45,20 @@ pub enum StepResult {
 
 impl Runner {
     /// creates a new instance. That instance is not valid for use yet, you must call init first
-    pub fn new(ByteCode { text, data }: ByteCode) -> Self {
+    pub fn new(
+        ByteCode {
+            text,
+            data,
+            arg_specs,
+            mode_specs,
+        }: ByteCode,
+    ) -> Self {
+        let mut mem: vm::Memory = data.into();
+        mem.arg_specs = arg_specs;
+        mem.mode_specs = mode_specs;
         Runner {
             text,
-            mem: data.into(),
+            mem,
             pc: std::ptr::null(),
         }
     }
@@ -55,8 +69,12 @@ impl Runner {
     }
 
     /// executes the current byte code instruction and adjustst the pc
-    /// will deref a null pointer if init wasn't called yet
+    /// will deref a null pointer if init wasn't called yet. Fails if a signal was received, see
+    /// [vm::signals]
     pub unsafe fn step(&mut self) -> StepResult {
+        if let Err(e) = vm::signals::check() {
+            return StepResult::Err(e);
+        }
         let disc_ptr = self.pc as *const u16;
         use vm::ExecOutcome::*;
         match OpCode::dispatch_discriminant(*disc_ptr, self.pc, &mut self.mem) {
@@ -65,7 +83,8 @@ impl Runner {
                 StepResult::Ok
             }
             Ok(ExitCode(res)) => StepResult::Done(res),
-            Err(e) => StepResult::Err(e),
+            // e.g. a program that was stopped by the signal too
+            Err(e) => StepResult::Err(vm::signals::check().err().unwrap_or(e)),
         }
     }
 
@@ -23,6 +23,14 @@ pub struct ByteCodeBuilder {
     /// Represents the starting index on the stack for each scope, as well as the AST-ID
     /// of the node that created this scope
     pub scope_starts: Vector<(usize, usize)>,
+    /// The main sections, see [OpCode::ParseArgs]
+    pub arg_specs: Vector<ArgSpec>,
+    /// see [OpCode::SelectMode]
+    pub mode_specs: Vector<ModesSpec>,
+    /// The path of the script, which is the value of FILE
+    pub file: String,
+    /// The line of each AST node, by its id. LINE is the line it's used in
+    pub lines: Vector<usize>,
 }
 
 /// Util type for the Builder
@@ -43,6 +51,18 @@ pub enum DataTypeInfo {
     HeapTypeInfo { dtype: HeapType, owner_idx: Owner },
     StackType(StackType),
     CallableType(CallableType, Box<Signature>),
+    /// A fragment bound with let, and the symbols where it was defined. It isn't a value at
+    /// runtime, a higher order built in that it's passed to compiles it in place, see
+    /// [ByteCodeBuilder::bound_fragment]
+    FragmentInfo(Box<FnFragment>, Box<Signature>, Scopes<String, usize>),
+}
+
+/// The stack and the symbols of the code around a frame, see [ByteCodeBuilder::enter_frame]
+#[derive(Debug, Clone)]
+pub struct OuterFrame {
+    pub symbol_table: Scopes<String, usize>,
+    pub stack_info: Vector<DataInfo>,
+    pub scope_starts: Vector<(usize, usize)>,
 }
 
 #[derive(Debug, Clone)]
@@ -70,11 +90,29 @@ impl DataTypeInfo {
             | DataTypeInfo::HeapTypeInfo { dtype, .. } => DataType::HeapType(dtype),
             DataTypeInfo::StackType(st) => DataType::StackType(st),
             DataTypeInfo::CallableType(ct, sign) => DataType::Callable(ct, sign),
+            DataTypeInfo::FragmentInfo(_, sign, _) => {
+                DataType::Callable(CallableType::FnFragment, sign)
+            }
         }
     }
 }
 
 impl ByteCodeBuilder {
+    /// appends an opcode to the text, and records the ast node it was generated from
+    pub fn push_opcode(&mut self, opcode: OpCode, ast_id: usize) {
+        self.text.push_back(opcode);
+        self.ast_ids.push_back(ast_id);
+    }
+
+    /// lets the jump at jump_idx in the text jump to the opcode at target_idx
+    pub fn set_jump_target(&mut self, jump_idx: usize, target_idx: usize) {
+        let mut jump = self.text[jump_idx];
+        *jump
+            .jump_target_mut()
+            .expect("setting the jump target of an opcode that doesn't jump") = target_idx as isize;
+        self.text.set(jump_idx, jump);
+    }
+
     /// creates an entry in the symbol table for the current top value on the stack
     pub fn add_symbol_for_stack_top(&mut self, symbol_name: &str) {
         assert!(
@@ -92,25 +130,37 @@ impl ByteCodeBuilder {
     }
 
     /// adds a value to the datasection, and pushes an Instruction to put a ref to that value onto the stack
-    pub fn add_to_datasection_and_push_ref(&mut self, val: ComptimeValue, ast_id: usize) {
+    pub fn add_to_datasection_and_push_ref(
+        &mut self,
+        val: ComptimeValue,
+        dtype: HeapType,
+        ast_id: usize,
+    ) {
         self.data.push_back(val);
         let dsec_idx = self.data.len() - 1;
-        self.text.push_back(OpCode::PushDataSecRef(dsec_idx));
+        self.push_opcode(OpCode::PushDataSecRef(dsec_idx), ast_id);
         self.stack_info.push_back(DataInfo {
             ast_id,
-            type_info: DataTypeInfo::DataSecTypeInfo {
-                dtype: HeapType::Str,
-                dsec_idx,
-            },
+            type_info: DataTypeInfo::DataSecTypeInfo { dtype, dsec_idx },
         });
     }
 
     /// finds the stack index for the symbol and pushes an instruction to the text that coppies
     /// that entry to the stack top
-    pub fn copy_symbol_target_to_stack_top(&mut self, symbol: &str) -> Result<(), ()> {
-        let entry_idx = self.symbol_table.find_entry(symbol).ok_or(())?;
-        self.text.push_back(OpCode::RepushStackEntry(*entry_idx));
-        let mut entry = self.stack_info[*entry_idx].clone();
+    pub fn copy_symbol_target_to_stack_top(
+        &mut self,
+        symbol: &str,
+        ast_id: usize,
+    ) -> Result<(), ()> {
+        let entry_idx = *self.symbol_table.find_entry(symbol).ok_or(())?;
+        self.repush_stack_entry(entry_idx, ast_id);
+        Ok(())
+    }
+
+    /// writes an opcode to copy the stack entry to the stack top, the copy doesn't own the value
+    pub fn repush_stack_entry(&mut self, entry_idx: usize, ast_id: usize) {
+        self.push_opcode(OpCode::RepushStackEntry(entry_idx), ast_id);
+        let mut entry = self.stack_info[entry_idx].clone();
         if let DataTypeInfo::HeapTypeInfo {
             dtype,
             owner_idx: Owner::Me,
@@ -118,16 +168,15 @@ impl ByteCodeBuilder {
         {
             entry.type_info = DataTypeInfo::HeapTypeInfo {
                 dtype,
-                owner_idx: Owner::Some(*entry_idx),
+                owner_idx: Owner::Some(entry_idx),
             }
         }
         self.stack_info.push_back(entry);
-        Ok(())
     }
 
     /// writes an opcode to push a primitve to the stack, also updates the stack state
     pub fn push_primitive_to_stack(&mut self, val: CopyValue, ast_id: usize) {
-        self.text.push_back(OpCode::PushPrimitive(val));
+        self.push_opcode(OpCode::PushPrimitive(val), ast_id);
         let info = DataInfo {
             ast_id,
             type_info: DataTypeInfo::StackType(StackType::from(val)),
@@ -135,6 +184,50 @@ impl ByteCodeBuilder {
         self.stack_info.push_back(info);
     }
 
+    /// binds a fragment to the stack top, see [DataTypeInfo::FragmentInfo]. A unit takes its
+    /// place at runtime
+    pub fn push_fragment(&mut self, fragment: &FnFragment, sign: Signature) {
+        let symbols = self.symbol_table.clone();
+        self.push_primitive_to_stack(CopyValue::Unit, fragment.id);
+        self.stack_info.back_mut().unwrap().type_info =
+            DataTypeInfo::FragmentInfo(Box::new(fragment.clone()), Box::new(sign), symbols);
+    }
+
+    /// the fragment the symbol is bound to, with its signature, and the symbols where it was
+    /// defined. Its body needs to be compiled with them, so that it refers to the same values
+    /// wherever it's used
+    pub fn bound_fragment(
+        &self,
+        symbol: &str,
+    ) -> Option<(FnFragment, Signature, Scopes<String, usize>)> {
+        let entry_idx = *self.symbol_table.find_entry(symbol)?;
+        match &self.stack_info[entry_idx].type_info {
+            DataTypeInfo::FragmentInfo(fragment, sign, symbols) => Some((
+                fragment.as_ref().clone(),
+                sign.as_ref().clone(),
+                symbols.clone(),
+            )),
+            _ => None,
+        }
+    }
+
+    /// starts code that runs in its own frame, like the fragment of a lazy sequence. Its stack
+    /// starts empty, the stack indices in it are relative to the start of the frame
+    pub fn enter_frame(&mut self) -> OuterFrame {
+        OuterFrame {
+            symbol_table: std::mem::take(&mut self.symbol_table),
+            stack_info: std::mem::take(&mut self.stack_info),
+            scope_starts: std::mem::take(&mut self.scope_starts),
+        }
+    }
+
+    /// continues the code around the frame
+    pub fn leave_frame(&mut self, outer: OuterFrame) {
+        self.symbol_table = outer.symbol_table;
+        self.stack_info = outer.stack_info;
+        self.scope_starts = outer.scope_starts;
+    }
+
     /// removes the n topmost entries from the stack_info.
     pub fn pop_stack_entries(&mut self, n: usize) {
         for _ in 0..n {
@@ -151,7 +244,7 @@ impl ByteCodeBuilder {
             } else {
                 OpCode::Pop
             };
-            self.text.push_back(opcode);
+            self.push_opcode(opcode, entry.ast_id);
         }
     }
 
@@ -171,6 +264,7 @@ impl ByteCodeBuilder {
                 },
             }),
             DataType::Callable(_, _) => unimplemented!(),
+            DataType::Var(_) => panic!("type variables are replaced during type inference"),
         }
     }
 
@@ -202,8 +296,8 @@ impl ByteCodeBuilder {
             };
             if let Some(code) = maybe_opcode {
                 // There is a cast. Apply and return true.
-                self.text.push_back(code);
                 let old_entry = self.stack_info.pop_back().unwrap();
+                self.push_opcode(code, old_entry.ast_id);
                 self.create_value_in_memory(
                     target_tmpl
                         .concrete_type()
@@ -231,10 +325,10 @@ impl ByteCodeBuilder {
     /// a non owning ref is returned, and the owner is destroyed, ownership is stolen before the
     /// destruction.
     pub fn collapse_scope(&mut self) {
-        let (scope_start_idx, ast_id) = self.scope_starts.back().unwrap();
-        if *scope_start_idx == self.stack_info.len() {
+        let (scope_start_idx, ast_id) = *self.scope_starts.back().unwrap();
+        if scope_start_idx == self.stack_info.len() {
             // the scope was empty, we simply return a unit
-            self.push_primitive_to_stack(CopyValue::Unit, *ast_id);
+            self.push_primitive_to_stack(CopyValue::Unit, ast_id);
         } else {
             let res_index = self.stack_info.len() - 1;
             let mut res_entry = self.stack_info[res_index].clone();
@@ -250,7 +344,7 @@ impl ByteCodeBuilder {
                 // The result is a heap ref. We need to check owner ship
                 match new_owner {
                     Owner::Some(orig_owner_idx) => {
-                        if *orig_owner_idx >= *scope_start_idx {
+                        if *orig_owner_idx >= scope_start_idx {
                             // the owner will die with this scope, and we want to return the value
                             // so it can't be deleted. So we steal ownership from the owner
                             self.stack_info[*orig_owner_idx].type_info.disown();
@@ -267,9 +361,14 @@ impl ByteCodeBuilder {
                     }
                 }
             }
-            self.text.push_back(OpCode::StackTopToReg(0));
+            if let DataTypeInfo::FragmentInfo(_, sign, _) = &res_entry.type_info {
+                // the symbols it refers to might not outlive the scope
+                res_entry.type_info =
+                    DataTypeInfo::CallableType(CallableType::FnFragment, sign.clone());
+            }
+            self.push_opcode(OpCode::StackTopToReg(0), ast_id);
             self.pop_stack_entries(self.stack_info.len() - scope_start_idx);
-            self.text.push_back(OpCode::ReadReg(0));
+            self.push_opcode(OpCode::ReadReg(0), ast_id);
             self.stack_info.push_back(res_entry);
         }
         self.scope_starts.pop_back().unwrap();
@@ -277,16 +376,27 @@ impl ByteCodeBuilder {
     }
 
     pub fn build(mut self) -> (ByteCode, DebugInformation) {
-        self.text.push_back(OpCode::Exit(0));
+        // the exit is not generated by any node, the root node is the best fit
+        self.push_opcode(OpCode::Exit(0), 0);
         let final_opcode_sizes: Vec<_> = self.text.iter().map(|oc| oc.serialized_size()).collect();
-        let index = (0..final_opcode_sizes.len()).map(|i| final_opcode_sizes[0..i].iter().sum());
-        let final_index = index.enumerate().map(|(a, b)| (b, a)).collect();
+        let index: Vec<usize> = (0..final_opcode_sizes.len())
+            .map(|i| final_opcode_sizes[0..i].iter().sum())
+            .collect();
+        // jump targets are opcode indices until now
+        for (i, opcode) in self.text.iter_mut().enumerate() {
+            if let Some(target) = opcode.jump_target_mut() {
+                *target = index[*target as usize] as isize - index[i] as isize;
+            }
+        }
+        let final_index = index.into_iter().enumerate().map(|(a, b)| (b, a)).collect();
         let final_text = self.text.iter().flat_map(|c| c.to_bytes()).collect();
 
         (
             ByteCode {
                 text: final_text,
                 data: self.data.into_iter().collect(),
+                arg_specs: self.arg_specs.into_iter().collect(),
+                mode_specs: self.mode_specs.into_iter().collect(),
             },
             DebugInformation {
                 ast_ids: self.ast_ids.into_iter().collect(),
@@ -26,9 +26,18 @@ pub trait RefRequirements: Debug + Clone + PartialEq + Eq + Hash {}
 pub enum Value<RefT: RefRequirements> {
     Str(String),
     Keyword(String),
+    /// contains the pattern, the compiled regexes are cached by the [Memory](crate::vm::Memory)
+    Regex(String),
     Vec(Vector<Data<RefT>>),
     Dict(HashMap<Data<RefT>, Data<RefT>>),
     Set(HashSet<Data<RefT>>),
+    /// A value of the dynamic json type. The wrapper gives scalars a place on the heap too, so
+    /// all json values are stored the same way. It owns the wrapped data
+    Json(Data<RefT>),
+    /// A lazy sequence, contains the index of its state in the [Memory](crate::vm::Memory)
+    Seq(usize),
+    /// A value of an optional type, it owns the wrapped data if there is some
+    Opt(Option<Data<RefT>>),
 }
 
 pub type ComptimeValue = Value<ComptimeRef>;
@@ -40,9 +49,34 @@ pub enum CopyValue {
     Int(i64),
     Float(OrderedFloat<f64>),
     Bool(bool),
+    ProcHandle(ProcHandle),
+    Stream(Stream),
     Unit,
 }
 
+/// Refers to a process that was started in the background.
+///
+/// The process itself is owned by the [Memory](crate::vm::Memory), idx is the index into its
+/// process table.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Hash, PartialEq, Eq)]
+pub struct ProcHandle {
+    pub idx: usize,
+    pub pid: u32,
+}
+
+/// One of the standard streams of the script, see the globals IN, OUT and ERR. Null discards
+/// what is written to it, and is empty when read, see the global NULL. Capture only is a target
+/// of the output of programs in the background, which is collected for captured, see the global
+/// CAPTURE
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Hash, PartialEq, Eq)]
+pub enum Stream {
+    In,
+    Out,
+    Err,
+    Null,
+    Capture,
+}
+
 /// Represents all possible values.
 ///
 /// That means either a real value or a reference to a value.
@@ -72,6 +106,18 @@ impl RefRequirements for ComptimeRef {}
 pub type ComptimeData = Data<ComptimeRef>;
 pub type RuntimeData = Data<RuntimeRef>;
 
+/// Built in argument that accepts data of any type
+#[derive(Debug)]
+pub struct AnyData(pub RuntimeData);
+
+/// Built in argument that accepts json values, contains the wrapped data
+#[derive(Debug)]
+pub struct JsonData(pub RuntimeData);
+
+/// Built in argument that accepts lazy sequences, contains the index of the sequence
+#[derive(Debug)]
+pub struct SeqData(pub usize);
+
 pub trait TryFromRef<SrcT>: Sized {
     unsafe fn try_from_ref(s: &SrcT) -> Option<Self>;
 }
@@ -111,6 +157,11 @@ impl<RefT: RefRequirements + Display> Display for Value<RefT> {
                 )
             }
             Value::Keyword(kw) => write!(f, ":{}", kw),
+            Value::Regex(pattern) => write!(f, "r\"{}\"", pattern),
+            Value::Json(d) => Display::fmt(d, f),
+            Value::Seq(_) => write!(f, "<sequence>"),
+            Value::Opt(Some(d)) => Display::fmt(d, f),
+            Value::Opt(None) => write!(f, "none"),
         }
     }
 }
@@ -179,6 +230,18 @@ impl From<f64> for CopyValue {
     }
 }
 
+impl From<ProcHandle> for CopyValue {
+    fn from(x: ProcHandle) -> Self {
+        CopyValue::ProcHandle(x)
+    }
+}
+
+impl From<Stream> for CopyValue {
+    fn from(x: Stream) -> Self {
+        CopyValue::Stream(x)
+    }
+}
+
 impl From<()> for CopyValue {
     fn from(_: ()) -> Self {
         CopyValue::Unit
@@ -218,6 +281,28 @@ impl TryFrom<CopyValue> for bool {
     }
 }
 
+impl TryFrom<CopyValue> for ProcHandle {
+    type Error = ();
+    fn try_from(v: CopyValue) -> Result<ProcHandle, ()> {
+        if let CopyValue::ProcHandle(h) = v {
+            Ok(h)
+        } else {
+            Err(())
+        }
+    }
+}
+
+impl TryFrom<CopyValue> for Stream {
+    type Error = ();
+    fn try_from(v: CopyValue) -> Result<Stream, ()> {
+        if let CopyValue::Stream(s) = v {
+            Ok(s)
+        } else {
+            Err(())
+        }
+    }
+}
+
 impl TryFrom<CopyValue> for () {
     type Error = ();
     fn try_from(v: CopyValue) -> Result<(), ()> {
@@ -236,6 +321,34 @@ impl TryFrom<CopyValue> for *const String {
     }
 }
 
+impl<T> TryFrom<CopyValue> for Vec<T> {
+    type Error = ();
+    fn try_from(_: CopyValue) -> Result<Self, Self::Error> {
+        Err(())
+    }
+}
+
+impl TryFrom<CopyValue> for AnyData {
+    type Error = ();
+    fn try_from(v: CopyValue) -> Result<Self, Self::Error> {
+        Ok(AnyData(Data::CopyVal(v)))
+    }
+}
+
+impl TryFrom<CopyValue> for JsonData {
+    type Error = ();
+    fn try_from(_: CopyValue) -> Result<Self, Self::Error> {
+        Err(())
+    }
+}
+
+impl TryFrom<CopyValue> for SeqData {
+    type Error = ();
+    fn try_from(_: CopyValue) -> Result<Self, Self::Error> {
+        Err(())
+    }
+}
+
 impl<T: TryFrom<CopyValue>> TryFromRef<CopyValue> for T {
     unsafe fn try_from_ref(s: &CopyValue) -> Option<Self> {
         (*s).try_into().ok()
@@ -249,6 +362,20 @@ impl Display for CopyValue {
             CopyValue::Unit => write!(f, "()"),
             CopyValue::Int(i) => write!(f, "{}", i),
             CopyValue::Float(fl) => write!(f, "{}", fl),
+            CopyValue::ProcHandle(h) => write!(f, "<process {}>", h.pid),
+            CopyValue::Stream(s) => Display::fmt(s, f),
+        }
+    }
+}
+
+impl Display for Stream {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Stream::In => write!(f, "<stdin>"),
+            Stream::Out => write!(f, "<stdout>"),
+            Stream::Err => write!(f, "<stderr>"),
+            Stream::Null => write!(f, "<null>"),
+            Stream::Capture => write!(f, "<capture>"),
         }
     }
 }
@@ -261,6 +388,7 @@ impl<RefT: RefRequirements> TryFromRef<Value<RefT>> for *const String {
         match s {
             Value::Str(s) => Some(s),
             Value::Keyword(kw) => Some(kw),
+            Value::Regex(pattern) => Some(pattern),
             _ => None,
         }
     }
@@ -279,6 +407,38 @@ where
     }
 }
 
+impl TryFromRef<Value<RuntimeRef>> for JsonData {
+    unsafe fn try_from_ref(s: &Value<RuntimeRef>) -> Option<Self> {
+        match s {
+            Value::Json(d) => Some(JsonData(d.clone())),
+            _ => None,
+        }
+    }
+}
+
+// json values are created at run time, so they are never in the data section
+impl TryFromRef<Value<ComptimeRef>> for JsonData {
+    unsafe fn try_from_ref(_: &Value<ComptimeRef>) -> Option<Self> {
+        None
+    }
+}
+
+impl TryFromRef<Value<RuntimeRef>> for SeqData {
+    unsafe fn try_from_ref(s: &Value<RuntimeRef>) -> Option<Self> {
+        match s {
+            Value::Seq(idx) => Some(SeqData(*idx)),
+            _ => None,
+        }
+    }
+}
+
+// sequences are created at run time too
+impl TryFromRef<Value<ComptimeRef>> for SeqData {
+    unsafe fn try_from_ref(_: &Value<ComptimeRef>) -> Option<Self> {
+        None
+    }
+}
+
 // ==============================================================================
 // TryFromRef<RefT>
 // ==============================================================================
@@ -288,6 +448,13 @@ impl<T> TryFromRef<()> for T {
     }
 }
 
+// the elements of a container in the data section can't be refs
+impl<T> TryFromRef<ComptimeRef> for T {
+    unsafe fn try_from_ref(_: &ComptimeRef) -> Option<Self> {
+        None
+    }
+}
+
 impl<T> TryFromRef<RuntimeRef> for T
 where
     T: TryFromRef<Value<RuntimeRef>> + TryFromRef<Value<ComptimeRef>>,
@@ -309,6 +476,36 @@ impl TryFromRef<RuntimeRef> for i64 {
     }
 }
 
+impl TryFromRef<RuntimeRef> for f64 {
+    unsafe fn try_from_ref(_: &RuntimeRef) -> Option<Self> {
+        None
+    }
+}
+
+impl TryFromRef<RuntimeRef> for bool {
+    unsafe fn try_from_ref(_: &RuntimeRef) -> Option<Self> {
+        None
+    }
+}
+
+impl TryFromRef<RuntimeRef> for ProcHandle {
+    unsafe fn try_from_ref(_: &RuntimeRef) -> Option<Self> {
+        None
+    }
+}
+
+impl TryFromRef<RuntimeRef> for Stream {
+    unsafe fn try_from_ref(_: &RuntimeRef) -> Option<Self> {
+        None
+    }
+}
+
+impl TryFromRef<RuntimeRef> for AnyData {
+    unsafe fn try_from_ref(s: &RuntimeRef) -> Option<Self> {
+        Some(AnyData(Data::Ref(s.clone())))
+    }
+}
+
 // ==============================================================================
 // TryFromRef<Data<RefT>>
 // ==============================================================================
@@ -11,11 +11,13 @@ use std::collections::BTreeMap;
 /// The downside is, that you cannot shrink it. In case this becomes a necessity, it would be possible,
 /// to additionally use a HashMap to map old indices to new ones.
 /// As this is used in the interpreter and should be as fast as possible, I use unsafe functions where it
-/// safes performance
+/// safes performance.
+/// The values are boxed, because the VM holds pointers to them, which would become invalid if the
+/// Vec reallocated.
 
 #[derive(Debug, Clone)]
 pub struct Heap<T> {
-    data: Vec<T>,
+    data: Vec<Box<T>>,
     free_indices: Vec<usize>,
     /// maps pointers to indices
     addrs: BTreeMap<*const T, usize>,
@@ -43,13 +45,13 @@ impl<T> Heap<T> {
 
     pub fn push(&mut self, val: T) -> usize {
         let idx = if let Some(idx) = self.free_indices.pop() {
-            self.data[idx] = val;
+            *self.data[idx] = val;
             idx
         } else {
-            self.data.push(val);
+            self.data.push(Box::new(val));
             self.data.len() - 1
         };
-        self.addrs.insert(&self.data[idx], idx);
+        self.addrs.insert(&*self.data[idx], idx);
         idx
     }
 
@@ -78,7 +80,7 @@ impl<'a, T> Iterator for Iter<'a, T> {
 
     fn next(&mut self) -> Option<Self::Item> {
         if let Some(i) = &mut self.pos {
-            let res = self.heap.data.get(*i);
+            let res = self.heap.data.get(*i).map(|b| &**b);
             self.pos = (*i + 1..self.heap.data.len()).find(|i| !self.heap.free_indices.contains(i));
             res
         } else {
@@ -24,4 +24,7 @@ pub use types::*;
 mod heap;
 pub use heap::*;
 
+mod arg_spec;
+pub use arg_spec::*;
+
 pub mod ast_macros;
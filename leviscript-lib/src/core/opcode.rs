@@ -23,7 +23,8 @@ pub enum OpCode {
     Exit(i32),
     PushDataSecRef(usize),
     PushPrimitive(CopyValue),
-    /// makes a copy of the entry at the given index and puts it on top of the stack
+    /// makes a copy of the entry at the given index and puts it on top of the stack. Stack
+    /// indices are relative to the frame, see [crate::vm::Memory::frame_base]
     RepushStackEntry(usize),
     /// coppies the stack top to the register addressed by the u8
     StackTopToReg(u8),
@@ -34,10 +35,237 @@ pub enum OpCode {
     /// Pops a value from the stack. Must be a reference. Frees the value behind
     /// the ref
     PopFree,
+    /// Pushes the elements of the list at the stack index onto the stack
+    SpreadVec(SpreadInfo),
+    /// Pushes the number of stack entries above the given index, as an Int. Used as the
+    /// argument count for variadic built ins, when it's only known at runtime
+    PushVarArgCount(usize),
+    /// Removes all stack entries from the given index on. Nothing is freed
+    TruncateStack(usize),
+    /// Parses the script arguments according to the arg spec with the given index, and pushes
+    /// the values in the order of [ArgSpec::bindings]. Prints the help, or a usage error, and
+    /// exits if the arguments ask for it
+    ParseArgs(usize),
+    /// Selects the mode of the modes spec with the given index, by the first script argument,
+    /// which is removed. Prints the help, or a usage error, and exits if the arguments ask for it
+    SelectMode(usize),
+    /// Moves the pc by the given offset, see [OpCode::jump_target_mut]
+    Jump(isize),
+    /// Jumps, unless the given mode was selected
+    JumpUnlessMode(ModeJump),
+    /// Pushes the value of a global, see [crate::vm::globals]
+    PushGlobal(Global),
+    /// Undoes the changes of the innermost scoped built in, after its body was evaluated, see
+    /// [crate::vm::built_ins::is_scoped]
+    LeaveScope,
+    /// Like [OpCode::LeaveScope] for measure, it replaces the value of the body by a pair of the
+    /// value and how long the body took
+    LeaveMeasure,
+    /// Pushes the arguments of the fragment of the innermost higher order built in for the next
+    /// element, or jumps by the offset if there is none, see [crate::vm::iteration]
+    IterNext(isize),
+    /// Hands the stack top to the innermost higher order built in, as the result of its fragment
+    IterCollect,
+    /// Ends the innermost higher order built in, and pushes its result
+    IterEnd,
+    /// Replaces the sequence below the captured values by a lazy one, which runs the fragment
+    /// at the offset for its elements, see [crate::vm::sequence::Fragment]
+    Lazy(LazyInfo),
+    /// Ends the code of a fragment that runs for the elements of a lazy sequence, its result is
+    /// the stack top
+    FragmentEnd,
     ToStr,
     ToBool,
+    /// Makes a regex from a str, fails if the pattern is invalid
+    ToRegex,
     Exec,
     StrCat,
+    Spawn,
+    IsRunning,
+    Wait,
+    Captured,
+    Kill,
+    Glob,
+    GlobStrict,
+    EscapeGlob,
+    GlobMatch,
+    Read,
+    Write,
+    Exists,
+    IsDir,
+    IsFile,
+    IsRelative,
+    Name,
+    Stem,
+    Extension,
+    Parent,
+    JoinPath,
+    Canonicalize,
+    ExpandHome,
+    ReadFile,
+    ReadLines,
+    WriteFile,
+    AppendFile,
+    Delete,
+    Copy,
+    Move,
+    MakeDir,
+    Split,
+    Lines,
+    Join,
+    Trim,
+    TrimStart,
+    TrimEnd,
+    Replace,
+    StartsWith,
+    EndsWith,
+    Contains,
+    Upper,
+    Lower,
+    Len,
+    ByteLen,
+    Slice,
+    ByteSlice,
+    Regex,
+    Matches,
+    Find,
+    FindAll,
+    Captures,
+    ReplaceAll,
+    FromJson,
+    ToJson,
+    ToJsonPretty,
+    JsonType,
+    JsonGet,
+    JsonAt,
+    JsonKeys,
+    JsonList,
+    JsonStr,
+    JsonInt,
+    JsonFloat,
+    JsonBool,
+    GetEnv,
+    GetEnvOr,
+    HasEnv,
+    SetEnv,
+    UnsetEnv,
+    WithEnv,
+    WithCwd,
+    WithTmpPath,
+    WithTmpDir,
+    Print,
+    Println,
+    Eprint,
+    Eprintln,
+    ReadLine,
+    ReadAll,
+    StdinLines,
+    Prompt,
+    Confirm,
+    Password,
+    Color,
+    BgColor,
+    Bold,
+    Dim,
+    Italic,
+    Underline,
+    IsTerminal,
+    TermWidth,
+    Progress,
+    WithSpinner,
+    Now,
+    Since,
+    AddTime,
+    TimeBetween,
+    FormatTime,
+    FormatUtc,
+    ParseTime,
+    ParseUtc,
+    Duration,
+    FormatDuration,
+    Sleep,
+    Measure,
+    ParseInt,
+    ParseIntOr,
+    ParseIntRadix,
+    ParseFloat,
+    ParseFloatOr,
+    ToFloat,
+    Fixed,
+    PadLeft,
+    PadRight,
+    FormatBytes,
+    Map,
+    Filter,
+    Fold,
+    Each,
+    For,
+    Any,
+    All,
+    SortBy,
+    MapIndexed,
+    Sort,
+    Unique,
+    Zip,
+    Enumerate,
+    Flatten,
+    FileLines,
+    OutputLines,
+    Range,
+    Take,
+    Skip,
+    ToList,
+    RangeInclusive,
+    RangeStep,
+    IsIn,
+    Index,
+    ExecRedirected,
+    SpawnRedirected,
+    OrElse,
+    IsSome,
+    First,
+    Second,
+}
+
+/// The argument of [OpCode::SpreadVec]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SpreadInfo {
+    /// stack index of the list to spread
+    pub stack_idx: usize,
+    /// the number of elements the list needs to have at least
+    pub min_len: usize,
+    /// if set, the list needs to have exactly min_len elements
+    pub exact: bool,
+}
+
+/// The argument of [OpCode::JumpUnlessMode]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ModeJump {
+    pub mode: usize,
+    pub offset: isize,
+}
+
+/// The argument of [OpCode::Lazy]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LazyInfo {
+    /// map, filter or mapIndexed
+    pub kind: IterKind,
+    /// the number of values the fragment captured, they are on top of the sequence
+    pub captured: usize,
+    /// the offset of the code of the fragment
+    pub offset: isize,
+}
+
+/// The argument of [OpCode::PushGlobal]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Global {
+    Argv,
+    Env,
+    In,
+    Out,
+    Err,
+    Null,
+    Capture,
 }
 
 impl OpCode {
@@ -54,8 +282,27 @@ impl OpCode {
         };
     }
 
+    /// The target of a jump.
+    ///
+    /// While the byte code is being built, it's the index of the target opcode. When it's built,
+    /// it's converted to the offset in bytes from the jump to the target.
+    pub fn jump_target_mut(&mut self) -> Option<&mut isize> {
+        use OpCode::*;
+        match self {
+            Jump(offset) => Some(offset),
+            JumpUnlessMode(ModeJump { offset, .. }) => Some(offset),
+            IterNext(offset) => Some(offset),
+            Lazy(LazyInfo { offset, .. }) => Some(offset),
+            _ => None,
+        }
+    }
+
     pub fn get_cast(from: &DataType, to: &DataType) -> Option<Self> {
+        use OpCode::*;
         match (from, to) {
+            (DataType::HeapType(HeapType::Str), DataType::HeapType(HeapType::Regex)) => {
+                Some(ToRegex)
+            }
             _ => None,
         }
     }
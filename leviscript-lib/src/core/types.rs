@@ -2,7 +2,7 @@
 
 use super::CopyValue;
 
-use std::collections::BTreeSet;
+use std::collections::{BTreeMap, BTreeSet};
 
 /// Represents TypeInformation at compile time
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
@@ -10,15 +10,33 @@ pub enum DataType {
     HeapType(HeapType),
     StackType(StackType),
     Callable(CallableType, Box<Signature>),
+    /// A type variable, which only occurs in the signatures of generic built ins. It stands for
+    /// the same type everywhere in the signature, which is found out for each call during type
+    /// inference
+    Var(u8),
 }
 
+/// The types that the type variables of a generic signature stand for, by their number
+pub type TypeVars = BTreeMap<u8, DataType>;
+
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
 pub enum HeapType {
     Str,
     Keyword,
+    Regex,
+    /// The dynamic type of parsed json, see the json built ins
+    Json,
     Vec(Box<DataType>),
     Dict(Box<DataType>, Box<DataType>),
     Set(Box<DataType>),
+    /// A lazy sequence, the elements are produced when they are consumed, see
+    /// [vm::sequence](crate::vm::sequence)
+    Seq(Box<DataType>),
+    /// Two values, whose types may differ, e.g. the result of measure. At run time it's a list
+    /// of the two. See first and second
+    Pair(Box<DataType>, Box<DataType>),
+    /// A value that may be missing, e.g. the result of getEnv. See orElse and isSome
+    Opt(Box<DataType>),
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
@@ -32,6 +50,8 @@ pub enum StackType {
     Int,
     Float,
     Bool,
+    ProcHandle,
+    Stream,
     Unit,
 }
 
@@ -64,6 +84,8 @@ impl From<CopyValue> for StackType {
             Int(_) => Self::Int,
             Float(_) => Self::Float,
             Bool(_) => Self::Bool,
+            ProcHandle(_) => Self::ProcHandle,
+            Stream(_) => Self::Stream,
             Unit => Self::Unit,
         }
     }
@@ -79,15 +101,121 @@ impl DataType {
     pub fn vec(self) -> Self {
         Self::HeapType(HeapType::Vec(Box::new(self)))
     }
+    pub fn seq(self) -> Self {
+        Self::HeapType(HeapType::Seq(Box::new(self)))
+    }
+    pub fn opt(self) -> Self {
+        Self::HeapType(HeapType::Opt(Box::new(self)))
+    }
+    pub fn pair(first: DataType, second: DataType) -> Self {
+        Self::HeapType(HeapType::Pair(Box::new(first), Box::new(second)))
+    }
+    pub fn dict(key: DataType, value: DataType) -> Self {
+        Self::HeapType(HeapType::Dict(Box::new(key), Box::new(value)))
+    }
     pub fn str() -> Self {
         Self::HeapType(HeapType::Str)
     }
+    pub fn regex() -> Self {
+        Self::HeapType(HeapType::Regex)
+    }
+    pub fn json() -> Self {
+        Self::HeapType(HeapType::Json)
+    }
     pub fn unit() -> Self {
         Self::StackType(StackType::Unit)
     }
     pub fn int() -> Self {
         Self::StackType(StackType::Int)
     }
+    pub fn float() -> Self {
+        Self::StackType(StackType::Float)
+    }
+    pub fn bool() -> Self {
+        Self::StackType(StackType::Bool)
+    }
+    pub fn proc_handle() -> Self {
+        Self::StackType(StackType::ProcHandle)
+    }
+    pub fn stream() -> Self {
+        Self::StackType(StackType::Stream)
+    }
+    pub fn var(n: u8) -> Self {
+        Self::Var(n)
+    }
+    pub fn fragment(sign: Signature) -> Self {
+        Self::Callable(CallableType::FnFragment, Box::new(sign))
+    }
+
+    /// whether the type contains type variables
+    pub fn is_generic(&self) -> bool {
+        match self {
+            Self::Var(_) => true,
+            Self::HeapType(
+                HeapType::Vec(t) | HeapType::Set(t) | HeapType::Seq(t) | HeapType::Opt(t),
+            ) => t.is_generic(),
+            Self::HeapType(HeapType::Dict(a, b) | HeapType::Pair(a, b)) => {
+                a.is_generic() || b.is_generic()
+            }
+            Self::HeapType(_) | Self::StackType(_) => false,
+            Self::Callable(_, sign) => sign.is_generic(),
+        }
+    }
+
+    /// Binds the type variables in self, so that it becomes t.
+    ///
+    /// Returns false if t has a different shape, or if a variable is bound to another type
+    /// already. Variables bound before the failure stay bound
+    pub fn unify(&self, t: &DataType, vars: &mut TypeVars) -> bool {
+        use HeapType::*;
+        match (self, t) {
+            (Self::Var(n), t) => match vars.get(n) {
+                Some(bound) => bound == t,
+                None => {
+                    vars.insert(*n, t.clone());
+                    true
+                }
+            },
+            (Self::HeapType(Vec(a)), Self::HeapType(Vec(b)))
+            | (Self::HeapType(Set(a)), Self::HeapType(Set(b)))
+            | (Self::HeapType(Seq(a)), Self::HeapType(Seq(b)))
+            | (Self::HeapType(Opt(a)), Self::HeapType(Opt(b))) => a.unify(b, vars),
+            (Self::HeapType(Dict(ak, av)), Self::HeapType(Dict(bk, bv)))
+            | (Self::HeapType(Pair(ak, av)), Self::HeapType(Pair(bk, bv))) => {
+                ak.unify(bk, vars) && av.unify(bv, vars)
+            }
+            (Self::Callable(_, a), Self::Callable(_, b)) => {
+                let unify_sets =
+                    |a: &TypeSet, b: &TypeSet, vars: &mut TypeVars| match b.concrete_type() {
+                        Some(b) => a.unify(b, vars),
+                        None => a == b,
+                    };
+                a.args.len() == b.args.len()
+                    && a.args
+                        .iter()
+                        .zip(&b.args)
+                        .all(|(a, b)| unify_sets(a, b, vars))
+                    && unify_sets(&a.result, &b.result, vars)
+            }
+            (a, b) => a == b,
+        }
+    }
+
+    /// replaces the type variables that are bound
+    pub fn substitute(&self, vars: &TypeVars) -> DataType {
+        use HeapType::*;
+        match self {
+            Self::Var(n) => vars.get(n).cloned().unwrap_or(Self::Var(*n)),
+            Self::HeapType(Vec(t)) => t.substitute(vars).vec(),
+            Self::HeapType(Set(t)) => Self::HeapType(Set(Box::new(t.substitute(vars)))),
+            Self::HeapType(Seq(t)) => t.substitute(vars).seq(),
+            Self::HeapType(Opt(t)) => t.substitute(vars).opt(),
+            Self::HeapType(Dict(k, v)) => Self::dict(k.substitute(vars), v.substitute(vars)),
+            Self::HeapType(Pair(a, b)) => Self::pair(a.substitute(vars), b.substitute(vars)),
+            Self::Callable(ct, sign) => Self::Callable(ct.clone(), Box::new(sign.substitute(vars))),
+            t => t.clone(),
+        }
+    }
 }
 
 impl Signature {
@@ -137,6 +265,31 @@ impl Signature {
         }
     }
 
+    /// Whether the signature contains type variables. Generic built ins are instantiated for each
+    /// call, the type of the callee is the signature with the variables replaced
+    pub fn is_generic(&self) -> bool {
+        self.args
+            .iter()
+            .chain(&self.var_arg)
+            .chain([&self.result])
+            .any(TypeSet::is_generic)
+    }
+
+    /// whether one of the arguments is a function, see [vm::iteration](crate::vm::iteration)
+    pub fn takes_fragment(&self) -> bool {
+        self.args
+            .iter()
+            .any(|t| matches!(t.concrete_type(), Some(DataType::Callable(..))))
+    }
+
+    pub fn substitute(&self, vars: &TypeVars) -> Signature {
+        Signature {
+            args: self.args.iter().map(|t| t.substitute(vars)).collect(),
+            result: self.result.substitute(vars),
+            var_arg: self.var_arg.as_ref().map(|t| t.substitute(vars)),
+        }
+    }
+
     pub fn get_nth_arg(&self, n: usize) -> Option<&TypeSet> {
         if n < self.args.len() {
             Some(&self.args[n])
@@ -179,6 +332,38 @@ impl TypeSet {
             TypeSet::AllTypes => None,
         }
     }
+
+    pub fn is_generic(&self) -> bool {
+        match self {
+            TypeSet::SomeTypes(ts) => ts.iter().any(DataType::is_generic),
+            TypeSet::AllTypes => false,
+        }
+    }
+
+    /// Binds the type variables, so that one of the types in the set becomes t. The first one
+    /// that fits is taken
+    pub fn unify(&self, t: &DataType, vars: &mut TypeVars) -> bool {
+        match self {
+            TypeSet::SomeTypes(ts) => ts.iter().any(|candidate| {
+                let mut tried = vars.clone();
+                let fits = candidate.unify(t, &mut tried);
+                if fits {
+                    *vars = tried;
+                }
+                fits
+            }),
+            TypeSet::AllTypes => true,
+        }
+    }
+
+    pub fn substitute(&self, vars: &TypeVars) -> TypeSet {
+        match self {
+            TypeSet::SomeTypes(ts) => {
+                TypeSet::SomeTypes(ts.iter().map(|t| t.substitute(vars)).collect())
+            }
+            TypeSet::AllTypes => TypeSet::AllTypes,
+        }
+    }
 }
 
 impl From<DataType> for TypeSet {
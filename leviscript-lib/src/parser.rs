@@ -58,27 +58,443 @@ fn parse_phrase<'a>(pair: Pair<'a>, span_vec: &mut SpanVec<'a>) -> ParseResult<P
     let child = get_single_child(pair.into_inner());
     match child.as_rule() {
         Rule::expression => Ok(Phrase::Expr(parse_expression(child, span_vec)?)),
+        Rule::main_def => Ok(Phrase::Main(parse_main_def(
+            child,
+            span_vec,
+            ArgSections::default(),
+        )?)),
+        Rule::modes_def => Ok(Phrase::Modes(parse_modes_def(child, span_vec)?)),
+        Rule::dollar_line => Ok(Phrase::Expr(
+            parse_x_expression_as(child, span_vec, "exec")?.into(),
+        )),
         _ => unreachable!(),
     }
 }
 
+fn parse_main_def<'a>(
+    pair: Pair<'a>,
+    span_vec: &mut SpanVec<'a>,
+    mut sections: ArgSections<'a>,
+) -> ParseResult<Main> {
+    let id = span_vec.len();
+    span_vec.push(pair.as_span());
+    assert!(matches!(pair.as_rule(), Rule::main_def | Rule::mode_def));
+
+    let mut children: Vec<Pair> = pair.into_inner().collect();
+    let body = children.pop().unwrap();
+    let mut help = None;
+    let mut positionals = Positionals::Names {
+        names: vec![],
+        rest: None,
+    };
+    // spans of the names of the positionals. Together with the spans of options and flags, they
+    // become the ArgDefs
+    let mut positional_spans = vec![];
+    for child in children {
+        match child.as_rule() {
+            // the name of a mode is handled by parse_modes_def
+            Rule::kw_main | Rule::kw_mode | Rule::symbol => {}
+            Rule::positionals => {
+                let child = get_single_child(child.into_inner());
+                positionals = match child.as_rule() {
+                    Rule::symbol => {
+                        positional_spans.push(child.as_span());
+                        Positionals::List(child.as_str().into())
+                    }
+                    Rule::positional_names => {
+                        let mut names = vec![];
+                        let mut rest = None;
+                        for name in child.into_inner() {
+                            let name = if let Rule::rest_positional = name.as_rule() {
+                                rest = Some(names.len());
+                                get_single_child(name.into_inner())
+                            } else {
+                                name
+                            };
+                            positional_spans.push(name.as_span());
+                            names.push(name.as_str().into());
+                        }
+                        Positionals::Names { names, rest }
+                    }
+                    _ => unreachable!(),
+                };
+            }
+            Rule::help_str => help = Some(parse_help_str(child)),
+            Rule::options_section | Rule::flags_section => sections.add(child),
+            _ => unreachable!(),
+        }
+    }
+
+    let (options, option_spans): (Vec<_>, Vec<_>) = sections.options.into_iter().unzip();
+    let (flags, flag_spans): (Vec<_>, Vec<_>) = sections.flags.into_iter().unzip();
+    let spec = ArgSpec {
+        help,
+        options,
+        flags,
+        positionals,
+    };
+    let bindings = spec
+        .bindings()
+        .into_iter()
+        .zip(
+            option_spans
+                .into_iter()
+                .chain(flag_spans)
+                .chain(positional_spans),
+        )
+        .map(|((name, _), span)| ArgDef {
+            id: push_span(span, span_vec),
+            name,
+        })
+        .collect();
+    Ok(Main {
+        id,
+        spec,
+        bindings,
+        body: Box::new(parse_expression(body, span_vec)?),
+    })
+}
+
+/// The options and flags of the all section are added to each mode
+fn parse_modes_def<'a>(pair: Pair<'a>, span_vec: &mut SpanVec<'a>) -> ParseResult<Modes> {
+    let id = span_vec.len();
+    span_vec.push(pair.as_span());
+    assert!(matches!(pair.as_rule(), Rule::modes_def));
+
+    let mut help = None;
+    let mut shared = ArgSections::default();
+    let mut mode_infos = vec![];
+    let mut modes = vec![];
+    for child in pair.into_inner() {
+        match child.as_rule() {
+            Rule::all_def => {
+                for section in child.into_inner() {
+                    match section.as_rule() {
+                        Rule::kw_all => {}
+                        Rule::help_str => help = Some(parse_help_str(section)),
+                        _ => shared.add(section),
+                    }
+                }
+            }
+            Rule::mode_def => {
+                let name = child
+                    .clone()
+                    .into_inner()
+                    .find(|p| p.as_rule() == Rule::symbol)
+                    .unwrap();
+                let mode = parse_main_def(child, span_vec, shared.clone())?;
+                let short_help = mode.spec.help.as_ref().map(|h| {
+                    let first_paragraph = h.split("\n\n").next().unwrap();
+                    first_paragraph.to_string()
+                });
+                mode_infos.push((name.as_str().into(), short_help));
+                modes.push(mode);
+            }
+            _ => unreachable!(),
+        }
+    }
+    let (options, flags) = (
+        shared.options.into_iter().map(|(o, _)| o).collect(),
+        shared.flags.into_iter().map(|(f, _)| f).collect(),
+    );
+    Ok(Modes {
+        id,
+        spec: ModesSpec {
+            help,
+            options,
+            flags,
+            modes: mode_infos,
+        },
+        modes,
+    })
+}
+
+/// The options and flags of a main, mode or all section, with the spans of their definitions
+#[derive(Clone, Default)]
+struct ArgSections<'a> {
+    options: Vec<(OptionSpec, Span<'a>)>,
+    flags: Vec<(FlagSpec, Span<'a>)>,
+}
+
+impl<'a> ArgSections<'a> {
+    fn add(&mut self, section: Pair<'a>) {
+        match section.as_rule() {
+            Rule::options_section => {
+                for option in section.into_inner() {
+                    self.options
+                        .push((parse_option_def(option.clone()), option.as_span()));
+                }
+            }
+            Rule::flags_section => {
+                for flag in section.into_inner() {
+                    self.flags
+                        .push((parse_flag_def(flag.clone()), flag.as_span()));
+                }
+            }
+            _ => unreachable!(),
+        }
+    }
+}
+
+fn parse_option_def(pair: Pair) -> OptionSpec {
+    assert!(matches!(pair.as_rule(), Rule::option_def));
+    let mut option = OptionSpec {
+        names: ArgNames {
+            short: None,
+            long: None,
+        },
+        arg_type: ArgType::Str,
+        default: None,
+        help: None,
+    };
+    for child in pair.into_inner() {
+        match child.as_rule() {
+            Rule::short_name => option.names.short = Some(child.as_str().into()),
+            Rule::long_name => option.names.long = Some(child.as_str().into()),
+            Rule::arg_type => {
+                option.arg_type = match child.as_str() {
+                    "int" => ArgType::Int,
+                    _ => ArgType::Str,
+                }
+            }
+            Rule::option_default => {
+                let value = get_single_child(child.into_inner());
+                option.default = Some(match value.as_rule() {
+                    Rule::help_str => parse_help_str(value),
+                    _ => value.as_str().into(),
+                })
+            }
+            Rule::help_str => option.help = Some(parse_help_str(child)),
+            _ => unreachable!(),
+        }
+    }
+    option
+}
+
+fn parse_flag_def(pair: Pair) -> FlagSpec {
+    assert!(matches!(pair.as_rule(), Rule::flag_def));
+    let mut flag = FlagSpec {
+        names: ArgNames {
+            short: None,
+            long: None,
+        },
+        help: None,
+    };
+    for child in pair.into_inner() {
+        match child.as_rule() {
+            Rule::short_name => flag.names.short = Some(child.as_str().into()),
+            Rule::long_name => flag.names.long = Some(child.as_str().into()),
+            Rule::help_str => flag.help = Some(parse_help_str(child)),
+            _ => unreachable!(),
+        }
+    }
+    flag
+}
+
+/// d'...' strs are dedented: the indentation that all lines after the first have in common is
+/// removed, as well as leading and trailing empty lines. Other strs are taken as they are
+fn parse_help_str(pair: Pair) -> String {
+    assert!(matches!(pair.as_rule(), Rule::help_str));
+    let mut children = pair.into_inner();
+    let first_child = children.next().unwrap();
+    let text = match first_child.as_rule() {
+        Rule::dedent => children.next().unwrap().as_str(),
+        _ => return first_child.as_str().into(),
+    };
+    let mut lines = text.lines();
+    let first = lines.next().unwrap_or_default().trim();
+    let rest: Vec<_> = lines.collect();
+    let indent = rest
+        .iter()
+        .filter(|l| !l.trim().is_empty())
+        .map(|l| l.len() - l.trim_start().len())
+        .min()
+        .unwrap_or(0);
+    let rest = rest
+        .iter()
+        .map(|l| l.get(indent..).unwrap_or_default().trim_end());
+    let lines: Vec<_> = std::iter::once(first).chain(rest).collect();
+    lines.join("\n").trim().into()
+}
+
 fn parse_expression<'a>(pair: Pair<'a>, span_vec: &mut SpanVec<'a>) -> ParseResult<Expr> {
-    assert!(matches!(pair.as_rule(), Rule::expression | Rule::callee));
+    assert!(matches!(
+        pair.as_rule(),
+        Rule::expression | Rule::callee | Rule::call_arg
+    ));
 
-    let child = get_single_child(pair.into_inner());
-    Ok(match child.as_rule() {
-        Rule::block_expression => parse_block_expr(child, span_vec)?.into(),
-        Rule::x_expression => parse_x_expression(child, span_vec)?.into(),
-        Rule::str_lit => parse_str_lit(child, span_vec)?.into(),
-        Rule::let_expr => parse_let_expr(child, span_vec)?.into(),
-        Rule::int_lit => parse_int_lit(child, span_vec)?.into(),
-        Rule::symbol => parse_symbol_expr(child, span_vec)?.into(),
-        Rule::call => parse_call_expr(child, span_vec)?.into(),
-        Rule::fragment_def => parse_fragment_def(child, span_vec)?.into(),
+    let mut children: Vec<Pair> = pair.into_inner().collect();
+    if children.len() == 1 {
+        parse_operand(children.pop().unwrap(), span_vec)
+    } else {
+        parse_infix_expression(children, span_vec)
+    }
+}
+
+fn parse_operand<'a>(pair: Pair<'a>, span_vec: &mut SpanVec<'a>) -> ParseResult<Expr> {
+    Ok(match pair.as_rule() {
+        // parenthesized expressions
+        Rule::expression => parse_expression(pair, span_vec)?,
+        Rule::block_expression => parse_block_expr(pair, span_vec)?.into(),
+        Rule::bg_expression => parse_bg_expression(pair, span_vec)?.into(),
+        Rule::x_expression => parse_x_expression(pair, span_vec)?.into(),
+        Rule::glob_expression => parse_glob_expression(pair, span_vec)?.into(),
+        Rule::str_lit => parse_str_lit(pair, span_vec)?,
+        Rule::regex_lit => parse_regex_lit(pair, span_vec)?.into(),
+        Rule::let_expr => parse_let_expr(pair, span_vec)?.into(),
+        Rule::int_lit => parse_int_lit(pair, span_vec)?.into(),
+        Rule::symbol => parse_symbol_expr(pair, span_vec)?,
+        Rule::call => parse_call_expr(pair, span_vec)?.into(),
+        Rule::index_expression => parse_index_expression(pair, span_vec)?,
+        Rule::fragment_def => parse_fragment_def(pair, span_vec)?.into(),
+        Rule::splice => parse_splice(pair, span_vec)?.into(),
+        Rule::prog_call => parse_prog_call(pair, span_vec)?.into(),
+        Rule::redirected_call => parse_redirected_call(pair, span_vec)?.into(),
         _ => unreachable!(),
     })
 }
 
+/// a program call is a call to exec, with the program as first argument
+fn parse_prog_call<'a>(pair: Pair<'a>, span_vec: &mut SpanVec<'a>) -> ParseResult<Call> {
+    let id = span_vec.len();
+    span_vec.push(pair.as_span());
+    assert!(matches!(pair.as_rule(), Rule::prog_call));
+    let span = pair.as_span();
+    let callee = Box::new(synthetic_symbol("exec", span, span_vec));
+
+    let mut children = pair.into_inner();
+    let program = children.next().unwrap();
+    let program = match program.as_rule() {
+        Rule::prog_path => StrLit(
+            push_span(program.as_span(), span_vec),
+            program.as_str().into(),
+        )
+        .into(),
+        _ => parse_operand(program, span_vec)?,
+    };
+    let args = std::iter::once(Ok(program))
+        .chain(children.map(|p| parse_expression(p, span_vec)))
+        .collect::<Result<_, _>>()?;
+    Ok(Call { id, callee, args })
+}
+
+/// the call of exec or spawn becomes a call of execRedirected or spawnRedirected, which take the
+/// targets of stdin, stdout and stderr as first arguments. The targets of streams that aren't
+/// redirected are empty blocks, they evaluate to unit
+fn parse_redirected_call<'a>(pair: Pair<'a>, span_vec: &mut SpanVec<'a>) -> ParseResult<Call> {
+    assert!(matches!(pair.as_rule(), Rule::redirected_call));
+    let span = pair.as_span();
+    let mut children = pair.into_inner();
+    let call = children.next().unwrap();
+    let mut call = match call.as_rule() {
+        Rule::prog_call => parse_prog_call(call, span_vec)?,
+        Rule::bg_expression => parse_bg_expression(call, span_vec)?,
+        Rule::x_expression => parse_x_expression(call, span_vec)?,
+        _ => unreachable!(),
+    };
+    let mut targets: [Option<Expr>; 3] = [None, None, None];
+    for redirection in children {
+        let mut parts = redirection.into_inner();
+        let op = parts.next().unwrap().as_str();
+        let target = parse_expression(parts.next().unwrap(), span_vec)?;
+        // a later redirection of the same stream replaces an earlier one
+        match op {
+            "<@" => targets[0] = Some(target),
+            "@>" => targets[1] = Some(target),
+            "!>" => targets[2] = Some(target),
+            "!@>" => {
+                targets[1] = Some(target.clone());
+                targets[2] = Some(target);
+            }
+            _ => unreachable!(),
+        }
+    }
+    let Expr::Symbol(Symbol(_, name)) = &mut *call.callee else {
+        unreachable!()
+    };
+    *name = format!("{}Redirected", name);
+    let targets =
+        targets.map(|t| t.unwrap_or_else(|| Block(push_span(span, span_vec), vec![]).into()));
+    call.args.splice(0..0, targets);
+    Ok(call)
+}
+
+fn parse_splice<'a>(pair: Pair<'a>, span_vec: &mut SpanVec<'a>) -> ParseResult<Splice> {
+    let id = span_vec.len();
+    span_vec.push(pair.as_span());
+    assert!(matches!(pair.as_rule(), Rule::splice | Rule::xexpr_splice));
+
+    let child = get_single_child(pair.into_inner());
+    Ok(Splice {
+        id,
+        list: Box::new(parse_operand(child, span_vec)?),
+    })
+}
+
+/// returns the precedence of an infix operator, a higher value binds stronger, and the name of
+/// the built in that implements it
+fn infix_op_info(op: Rule) -> (u8, &'static str) {
+    match op {
+        Rule::glob_match_op => (1, "globMatch"),
+        Rule::membership_op => (1, "isIn"),
+        Rule::range_step_op => (2, "rangeStep"),
+        Rule::range_op => (3, "range"),
+        Rule::inclusive_range_op => (3, "rangeInclusive"),
+        Rule::path_join_op => (4, "joinPath"),
+        _ => unreachable!(),
+    }
+}
+
+/// parses operands that are joined by infix operators. All operators are left associative.
+/// An infix operation is desugared into a call of the built in that implements the operator.
+///
+/// `pairs` alternates between operands and operators, starting and ending with an operand
+fn parse_infix_expression<'a>(
+    pairs: Vec<Pair<'a>>,
+    span_vec: &mut SpanVec<'a>,
+) -> ParseResult<Expr> {
+    let mut pairs = pairs.into_iter();
+    let first = pairs.next().unwrap();
+    let mut operands = vec![(first.as_span(), parse_operand(first, span_vec)?)];
+    let mut operators: Vec<Pair> = vec![];
+    while let Some(op) = pairs.next() {
+        let rhs = pairs.next().unwrap();
+        let precedence = infix_op_info(op.as_rule()).0;
+        while matches!(operators.last(), Some(top) if infix_op_info(top.as_rule()).0 >= precedence)
+        {
+            reduce_infix_operation(&mut operands, operators.pop().unwrap(), span_vec);
+        }
+        operators.push(op);
+        operands.push((rhs.as_span(), parse_operand(rhs, span_vec)?));
+    }
+    while let Some(op) = operators.pop() {
+        reduce_infix_operation(&mut operands, op, span_vec);
+    }
+    assert!(operands.len() == 1);
+    Ok(operands.pop().unwrap().1)
+}
+
+/// replaces the two topmost operands with a call to the operators built in
+fn reduce_infix_operation<'a>(
+    operands: &mut Vec<(Span<'a>, Expr)>,
+    op: Pair<'a>,
+    span_vec: &mut SpanVec<'a>,
+) {
+    let (rhs_span, rhs) = operands.pop().unwrap();
+    let (lhs_span, lhs) = operands.pop().unwrap();
+    let span = lhs_span.start_pos().span(&rhs_span.end_pos());
+    let id = span_vec.len();
+    span_vec.push(span);
+    let callee_id = span_vec.len();
+    span_vec.push(op.as_span());
+    let call = Call {
+        id,
+        callee: Box::new(Symbol(callee_id, infix_op_info(op.as_rule()).1.into()).into()),
+        args: vec![lhs, rhs],
+    };
+    operands.push((span, call.into()));
+}
+
 fn parse_fragment_def<'a>(pair: Pair<'a>, span_vec: &mut SpanVec<'a>) -> ParseResult<FnFragment> {
     let id = span_vec.len();
     span_vec.push(pair.as_span());
@@ -119,17 +535,38 @@ fn parse_call_expr<'a>(pair: Pair<'a>, span_vec: &mut SpanVec<'a>) -> ParseResul
     })
 }
 
+/// `xs[i]` is desugared into a call of the index built in, `xs[i][j]` into nested calls
+fn parse_index_expression<'a>(pair: Pair<'a>, span_vec: &mut SpanVec<'a>) -> ParseResult<Expr> {
+    assert!(matches!(pair.as_rule(), Rule::index_expression));
+    let start = pair.as_span().start_pos();
+    let mut children = pair.into_inner();
+    let mut indexed = parse_operand(children.next().unwrap(), span_vec)?;
+    for index in children {
+        let span = start.span(&index.as_span().end_pos());
+        let id = push_span(span, span_vec);
+        let callee = Box::new(synthetic_symbol("index", index.as_span(), span_vec));
+        let index = parse_expression(get_single_child(index.into_inner()), span_vec)?;
+        indexed = Call {
+            id,
+            callee,
+            args: vec![indexed, index],
+        }
+        .into();
+    }
+    Ok(indexed)
+}
+
 fn parse_block_expr<'a>(pair: Pair<'a>, span_vec: &mut SpanVec<'a>) -> ParseResult<Block> {
     assert!(matches!(pair.as_rule(), Rule::block_expression));
     let child = get_single_child(pair.into_inner());
     parse_block(child, span_vec)
 }
 
-fn parse_symbol_expr<'a>(pair: Pair<'a>, span_vec: &mut SpanVec<'a>) -> ParseResult<Symbol> {
+fn parse_symbol_expr<'a>(pair: Pair<'a>, span_vec: &mut SpanVec<'a>) -> ParseResult<Expr> {
     let id = span_vec.len();
     span_vec.push(pair.as_span());
     assert!(matches!(pair.as_rule(), Rule::symbol));
-    Ok(Symbol(id, pair.as_str().into()))
+    Ok(Symbol(id, pair.as_str().into()).into())
 }
 
 fn parse_int_lit<'a>(pair: Pair<'a>, span_vec: &mut SpanVec<'a>) -> ParseResult<IntLit> {
@@ -178,9 +615,8 @@ fn parse_str_lit<'a>(pair: Pair<'a>, span_vec: &mut SpanVec<'a>) -> ParseResult<
     Ok(if children.len() == 1 {
         children[0].clone()
     } else {
-        span_vec.push(pair.as_span());
         Call {
-            callee: Box::new(Symbol(id + 1, "strcat".into()).into()),
+            callee: Box::new(synthetic_symbol("strcat", pair.as_span(), span_vec)),
             args: children,
             id,
         }
@@ -188,6 +624,13 @@ fn parse_str_lit<'a>(pair: Pair<'a>, span_vec: &mut SpanVec<'a>) -> ParseResult<
     })
 }
 
+fn parse_regex_lit<'a>(pair: Pair<'a>, span_vec: &mut SpanVec<'a>) -> ParseResult<RegexLit> {
+    let id = push_span(pair.as_span(), span_vec);
+    assert!(matches!(pair.as_rule(), Rule::regex_lit));
+    let pattern = get_single_child(pair.into_inner());
+    Ok(RegexLit(id, pattern.as_str().into()))
+}
+
 fn parse_str_lit_elem<'a>(pair: Pair<'a>, span_vec: &mut SpanVec<'a>) -> ParseResult<Expr> {
     let id = span_vec.len();
     span_vec.push(pair.as_span());
@@ -195,21 +638,72 @@ fn parse_str_lit_elem<'a>(pair: Pair<'a>, span_vec: &mut SpanVec<'a>) -> ParseRe
 
     let child = get_single_child(pair.into_inner());
     Ok(match child.as_rule() {
-        Rule::pure_quoted_str_lit => StrLit(id, child.as_str().into()).into(),
-        Rule::symbol => Symbol(id, child.as_str().into()).into(),
-        Rule::expression => parse_expression(child, span_vec)?,
+        Rule::pure_quoted_str_lit => StrLit(id, unescape(child.as_str())).into(),
+        Rule::symbol => parse_symbol_expr(child, span_vec)?,
+        Rule::sub_expr => parse_sub_expr(child, span_vec)?,
         _ => unreachable!(),
     })
 }
 
+/// resolves the escape sequences that the grammar allows in quoted strs: \", \$, \\ and \u{..}
+/// with a code point in hex
+fn unescape(s: &str) -> String {
+    let mut res = String::with_capacity(s.len());
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            res.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('u') => {
+                // the grammar ensures that the braces contain 1 to 6 hex digits
+                let hex: String = chars.by_ref().skip(1).take_while(|c| *c != '}').collect();
+                let code = u32::from_str_radix(&hex, 16).unwrap();
+                res.push(char::from_u32(code).unwrap_or(char::REPLACEMENT_CHARACTER));
+            }
+            Some(escaped) => res.push(escaped),
+            None => {}
+        }
+    }
+    res
+}
+
+/// like the call of exec, that the x-expression or the program call would be, but of spawn
+fn parse_bg_expression<'a>(pair: Pair<'a>, span_vec: &mut SpanVec<'a>) -> ParseResult<Call> {
+    assert!(matches!(pair.as_rule(), Rule::bg_expression));
+    let child = get_single_child(pair.into_inner());
+    if child.as_rule() == Rule::x_expression {
+        return parse_x_expression_as(child, span_vec, "spawn");
+    }
+    let mut call = parse_prog_call(child, span_vec)?;
+    let Expr::Symbol(Symbol(_, name)) = &mut *call.callee else {
+        unreachable!()
+    };
+    *name = "spawn".into();
+    Ok(call)
+}
+
 fn parse_x_expression<'a>(pair: Pair<'a>, span_vec: &mut SpanVec<'a>) -> ParseResult<Call> {
+    parse_x_expression_as(pair, span_vec, "exec")
+}
+
+/// parses an x-expression into a call of `built_in`, which gets the elements as arguments
+fn parse_x_expression_as<'a>(
+    pair: Pair<'a>,
+    span_vec: &mut SpanVec<'a>,
+    built_in: &str,
+) -> ParseResult<Call> {
     let id = span_vec.len();
     span_vec.push(pair.as_span());
     // this is pushed twice because we will insert an Expr node for the built in call, which does not
     // have a span. So we reuse the span of the Xexpr. Since the ID is based on the span vec, this is
     // necessary so the symbol node can have it's own id
     span_vec.push(pair.as_span());
-    assert!(matches!(pair.as_rule(), Rule::x_expression));
+    assert!(matches!(
+        pair.as_rule(),
+        Rule::x_expression | Rule::dollar_line
+    ));
 
     let args: Vec<Expr> = pair
         .into_inner()
@@ -217,25 +711,132 @@ fn parse_x_expression<'a>(pair: Pair<'a>, span_vec: &mut SpanVec<'a>) -> ParseRe
         .collect::<Result<_, _>>()?;
 
     Ok(Call {
-        callee: Box::new(Symbol(id + 1, "exec".into()).into()),
+        callee: Box::new(Symbol(id + 1, built_in.into()).into()),
         args,
         id,
     })
 }
 
 fn parse_xexpr_atom<'a>(pair: Pair<'a>, span_vec: &mut SpanVec<'a>) -> ParseResult<Expr> {
-    let id = span_vec.len();
-    span_vec.push(pair.as_span());
     assert!(matches!(pair.as_rule(), Rule::xexpr_elem));
 
     let child = get_single_child(pair.into_inner());
     Ok(match child.as_rule() {
-        Rule::symbol => Symbol(id, child.as_str().into()).into(),
-        Rule::xexpr_str => StrLit(id, child.as_str().into()).into(),
+        Rule::xexpr_word => parse_xexpr_word(child, span_vec)?,
+        Rule::xexpr_splice => parse_splice(child, span_vec)?.into(),
         _ => unreachable!(),
     })
 }
 
+/// The pieces of a word become a single argument. If there is more than one, they are
+/// concatenated with strcat
+fn parse_xexpr_word<'a>(pair: Pair<'a>, span_vec: &mut SpanVec<'a>) -> ParseResult<Expr> {
+    assert!(matches!(pair.as_rule(), Rule::xexpr_word));
+    let span = pair.as_span();
+
+    let mut pieces: Vec<Expr> = vec![];
+    for piece in pair.into_inner() {
+        match piece.as_rule() {
+            Rule::xexpr_str | Rule::xexpr_single_quoted_str => pieces
+                .push(StrLit(push_span(piece.as_span(), span_vec), piece.as_str().into()).into()),
+            Rule::symbol => pieces.push(parse_symbol_expr(piece, span_vec)?),
+            Rule::sub_expr => pieces.push(parse_sub_expr(piece, span_vec)?),
+            // the elements of a quoted string are pieces of the word too
+            Rule::quoted_str_lit => {
+                for elem in piece.into_inner() {
+                    pieces.push(parse_str_lit_elem(elem, span_vec)?);
+                }
+            }
+            _ => unreachable!(),
+        }
+    }
+    Ok(match pieces.len() {
+        // an empty quoted string is still an argument
+        0 => StrLit(push_span(span, span_vec), String::new()).into(),
+        1 => pieces.pop().unwrap(),
+        _ => Call {
+            id: push_span(span, span_vec),
+            callee: Box::new(synthetic_symbol("strcat", span, span_vec)),
+            args: pieces,
+        }
+        .into(),
+    })
+}
+
+fn parse_glob_expression<'a>(pair: Pair<'a>, span_vec: &mut SpanVec<'a>) -> ParseResult<Call> {
+    let id = span_vec.len();
+    span_vec.push(pair.as_span());
+    assert!(matches!(pair.as_rule(), Rule::glob_expression));
+    let span = pair.as_span();
+
+    let mut children = pair.into_inner();
+    let built_in = match children.next().unwrap().as_rule() {
+        Rule::glob_start => "glob",
+        Rule::strict_glob_start => "globStrict",
+        _ => unreachable!(),
+    };
+    let mut pattern_parts: Vec<Expr> = children
+        .map(|p| parse_glob_elem(p, span_vec))
+        .collect::<Result<_, _>>()?;
+    let pattern = if pattern_parts.len() == 1 {
+        pattern_parts.pop().unwrap()
+    } else {
+        Call {
+            id: push_span(span, span_vec),
+            callee: Box::new(synthetic_symbol("strcat", span, span_vec)),
+            args: pattern_parts,
+        }
+        .into()
+    };
+    Ok(Call {
+        id,
+        callee: Box::new(synthetic_symbol(built_in, span, span_vec)),
+        args: vec![pattern],
+    })
+}
+
+/// parses a part of a glob pattern. Interpolated values are escaped, so they are matched
+/// literally
+fn parse_glob_elem<'a>(pair: Pair<'a>, span_vec: &mut SpanVec<'a>) -> ParseResult<Expr> {
+    assert!(matches!(pair.as_rule(), Rule::glob_elem));
+    let span = pair.as_span();
+
+    let child = get_single_child(pair.into_inner());
+    let interpolated = match child.as_rule() {
+        Rule::glob_str => {
+            return Ok(StrLit(push_span(span, span_vec), child.as_str().into()).into())
+        }
+        Rule::symbol => parse_symbol_expr(child, span_vec)?,
+        Rule::sub_expr => parse_sub_expr(child, span_vec)?,
+        _ => unreachable!(),
+    };
+    Ok(Call {
+        id: push_span(span, span_vec),
+        callee: Box::new(synthetic_symbol("escapeGlob", span, span_vec)),
+        args: vec![interpolated],
+    }
+    .into())
+}
+
+/// parses `${expr}` in a string, an x-expression or a glob into the inner expression. Its value
+/// is converted with ToStr when the pieces are joined
+fn parse_sub_expr<'a>(pair: Pair<'a>, span_vec: &mut SpanVec<'a>) -> ParseResult<Expr> {
+    assert!(matches!(pair.as_rule(), Rule::sub_expr));
+    parse_expression(get_single_child(pair.into_inner()), span_vec)
+}
+
+/// pushes a span to the span vec, and returns the id that belongs to it
+fn push_span<'a>(span: Span<'a>, span_vec: &mut SpanVec<'a>) -> usize {
+    span_vec.push(span);
+    span_vec.len() - 1
+}
+
+/// creates a symbol, that refers to the built in that implements some syntax. Such a symbol has no
+/// span of its own, so it reuses the span of the syntax
+fn synthetic_symbol<'a>(name: &str, span: Span<'a>, span_vec: &mut SpanVec<'a>) -> Expr {
+    Symbol(push_span(span, span_vec), name.into()).into()
+}
+
 fn get_single_child(p: Pairs) -> Pair {
     let children: Vec<Pair> = p.collect();
     assert!(
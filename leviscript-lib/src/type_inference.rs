@@ -11,6 +11,7 @@ pub type Environment = Scopes<String, EnvironmentIdentifier>;
 pub enum EnvironmentIdentifier {
     AstId(usize),
     BuiltIn(usize),
+    Global(usize),
 }
 
 impl From<EnvironmentIdentifier> for usize {
@@ -18,6 +19,7 @@ impl From<EnvironmentIdentifier> for usize {
         match value {
             EnvironmentIdentifier::AstId(id) => id,
             EnvironmentIdentifier::BuiltIn(id) => id,
+            EnvironmentIdentifier::Global(id) => id,
         }
     }
 }
@@ -41,11 +43,29 @@ pub enum Error {
     #[error("Undefined Symbol {1}")]
     UndefinedSymbol(usize, String),
 
+    #[error("Undefined function {1}, programs need to be called with a leading !")]
+    UndefinedFunction(usize, String),
+
     #[error("For now, unused variables are forbidden: {1}")]
     UnusedVar(usize, String),
 
     #[error("Is not Callable")]
     CallingNonCallable(usize),
+
+    #[error("A script can only have one main section, or one set of modes")]
+    DuplicateMain(usize),
+
+    #[error("{1} needs a body as last argument")]
+    MissingBody(usize, String),
+
+    #[error("Found {2:?}, expected {1:?}")]
+    TypeMismatch(usize, TypeSet, DataType),
+
+    #[error("The fn fragment needs to take {1} argument(s)")]
+    FragmentArgCount(usize, usize),
+
+    #[error("Can't infer the type")]
+    UnknownType(usize),
 }
 
 impl Error {
@@ -54,7 +74,13 @@ impl Error {
             Self::NotCallable(id) => *id,
             Self::CallingNonCallable(id) => *id,
             Self::UndefinedSymbol(id, ..) => *id,
+            Self::UndefinedFunction(id, ..) => *id,
             Self::UnusedVar(id, ..) => *id,
+            Self::DuplicateMain(id) => *id,
+            Self::MissingBody(id, ..) => *id,
+            Self::TypeMismatch(id, ..) => *id,
+            Self::FragmentArgCount(id, ..) => *id,
+            Self::UnknownType(id) => *id,
         }
     }
 }
@@ -68,6 +94,13 @@ impl TypeInferable for Block {
         mut type_idx: TypeIndex,
     ) -> Result<(Environment, TypeIndex)> {
         let Block(id, phrases) = self;
+        if let Some(second_main) = phrases
+            .iter()
+            .filter(|p| matches!(p, Phrase::Main(_) | Phrase::Modes(_)))
+            .nth(1)
+        {
+            return Err(Error::DuplicateMain(second_main.get_id().into()));
+        }
         for phrase in phrases {
             (env, type_idx) = phrase.infer_types(env, type_idx)?;
         }
@@ -97,9 +130,9 @@ impl TypeInferable for FnFragment {
             }) = node
             {
                 let callee_id = match callee.as_ref() {
-                    Expr::Symbol(Symbol(_, callee_name)) => {
-                        env.find_entry(callee_name).expect("can't find callee type")
-                    }
+                    Expr::Symbol(Symbol(id, callee_name)) => env
+                        .find_entry(callee_name)
+                        .ok_or_else(|| Error::UndefinedFunction(*id, callee_name.clone()))?,
                     _ => panic!("not supported yet"),
                 };
                 let indices = find_matching_indices(&self.args, call_args);
@@ -107,10 +140,10 @@ impl TypeInferable for FnFragment {
                 else { return Err(Error::CallingNonCallable((*callee_id).into())) };
                 for (a_i, ca_i) in indices {
                     let ca_t = callee_sign.get_nth_arg(ca_i).unwrap();
-                    if let Some(t) = ca_t.concrete_type() {
-                        known_types[a_i] = Some(t.clone());
-                    } else {
-                        panic!("For now, this doesn't work");
+                    // a generic built in doesn't tell the type
+                    match ca_t.concrete_type().filter(|t| !t.is_generic()) {
+                        Some(t) => known_types[a_i] = Some(t.clone()),
+                        None => return Err(Error::UnknownType(self.args[a_i].id)),
                     }
                 }
             }
@@ -133,15 +166,17 @@ impl TypeInferable for FnFragment {
             env_for_body.add_entry(arg_def.name.clone(), arg_def.get_id());
         }
 
-        // find out the result type
+        // find out the result type, the types in the body are kept, a fragment bound with let is
+        // compiled where it's used
         (_, ti_for_body) = self.body.infer_types(env_for_body, ti_for_body)?;
-        let res_type = ti_for_body.get(&self.body.get_id()).unwrap();
+        type_idx = ti_for_body;
+        let res_type = type_idx.get(&self.body.get_id()).unwrap();
 
         // put own type into type_idx
         let arg_type_vec = self
             .args
             .iter()
-            .map(|a| ti_for_body.get(&a.get_id()).unwrap().clone().into())
+            .map(|a| type_idx.get(&a.get_id()).unwrap().clone().into())
             .collect();
 
         let my_type = DataType::Callable(
@@ -205,7 +240,29 @@ impl TypeInferable for Call {
         mut type_idx: TypeIndex,
     ) -> Result<(Environment, TypeIndex)> {
         let Call { id, callee, args } = self;
-        (_, type_idx) = callee.infer_types(env.clone(), type_idx)?;
+        // names are never looked up in the PATH, a typo must not run a program
+        (_, type_idx) = callee
+            .infer_types(env.clone(), type_idx)
+            .map_err(|e| match e {
+                Error::UndefinedSymbol(id, name) => Error::UndefinedFunction(id, name),
+                e => e,
+            })?;
+        if let (Expr::Symbol(Symbol(_, name)), DataType::Callable(CallableType::Builtin, sign)) =
+            (callee.as_ref(), type_idx.get(&callee.get_id()).unwrap())
+        {
+            if vm::built_ins::is_scoped(name) {
+                let sign = sign.as_ref().clone();
+                return infer_scoped_call(self, name, &sign, env, type_idx);
+            }
+            if name == "index" && args.len() == 2 {
+                let sign = sign.as_ref().clone();
+                return infer_index_call(self, &sign, env, type_idx);
+            }
+            if sign.is_generic() {
+                let sign = sign.as_ref().clone();
+                return infer_generic_call(self, &sign, env, type_idx);
+            }
+        }
         for arg in args {
             (_, type_idx) = arg.infer_types(env.clone(), type_idx)?;
         }
@@ -222,6 +279,197 @@ impl TypeInferable for Call {
     }
 }
 
+/// The body is inferred after the other arguments. If it's a fragment, its argument has the result
+/// type of the built in. See [vm::built_ins::is_scoped]
+fn infer_scoped_call(
+    call: &Call,
+    name: &str,
+    sign: &Signature,
+    env: Environment,
+    mut type_idx: TypeIndex,
+) -> Result<(Environment, TypeIndex)> {
+    let Call { id, args, .. } = call;
+    let Some((body, setup_args)) = args.split_last() else {
+        return Err(Error::MissingBody(*id, name.into()));
+    };
+    for arg in setup_args {
+        (_, type_idx) = arg.infer_types(env.clone(), type_idx)?;
+    }
+    let body_id = match body {
+        Expr::FnFragment(fragment) if fragment.args.len() == 1 => {
+            let arg = &fragment.args[0];
+            let arg_type = sign.result.concrete_type().unwrap().clone();
+            type_idx.insert(arg.get_id(), arg_type);
+            let mut env_for_body = env.clone();
+            env_for_body.add_entry(arg.name.clone(), arg.get_id());
+            (_, type_idx) = fragment.body.infer_types(env_for_body, type_idx)?;
+            fragment.body.get_id()
+        }
+        body => {
+            (_, type_idx) = body.infer_types(env.clone(), type_idx)?;
+            body.get_id()
+        }
+    };
+    copy_type_info(&mut type_idx, &body_id, EnvironmentIdentifier::AstId(*id));
+    if name == "measure" {
+        // a pair of the body and its duration, see OpCode::LeaveMeasure
+        let body_type = type_idx.get(&body_id).unwrap().clone();
+        type_idx.insert(
+            EnvironmentIdentifier::AstId(*id),
+            DataType::pair(body_type, DataType::int()),
+        );
+    }
+    Ok((env, type_idx))
+}
+
+/// Finds out which types the type variables of a generic built in stand for at this call. The
+/// signature with the variables replaced becomes the type of the callee.
+///
+/// Fragments are inferred last, the types of their arguments come from the other arguments. If
+/// the result of a fragment is a variable, it's bound to the type of the body
+fn infer_generic_call(
+    call: &Call,
+    sign: &Signature,
+    env: Environment,
+    mut type_idx: TypeIndex,
+) -> Result<(Environment, TypeIndex)> {
+    let Call { id, callee, args } = call;
+    let mut vars = TypeVars::new();
+    for (i, arg) in args.iter().enumerate() {
+        if matches!(arg, Expr::FnFragment(_)) {
+            continue;
+        }
+        (_, type_idx) = arg.infer_types(env.clone(), type_idx)?;
+        let actual = type_idx.get(&arg.get_id()).unwrap();
+        // other types are checked during compilation, where casts are possible
+        if let Some(expected) = sign.get_nth_arg(i).filter(|t| t.is_generic()) {
+            if !expected.unify(actual, &mut vars) {
+                return Err(Error::TypeMismatch(
+                    arg.get_id().into(),
+                    expected.substitute(&vars),
+                    actual.clone(),
+                ));
+            }
+        }
+    }
+    for (i, arg) in args.iter().enumerate() {
+        let Expr::FnFragment(fragment) = arg else {
+            continue;
+        };
+        match sign.get_nth_arg(i).and_then(TypeSet::concrete_type) {
+            Some(DataType::Callable(_, fragment_sign)) => {
+                type_idx =
+                    infer_passed_fragment(fragment, fragment_sign, &mut vars, &env, type_idx)?;
+            }
+            // the compiler reports that a fragment is passed where none is expected
+            _ => (_, type_idx) = arg.infer_types(env.clone(), type_idx)?,
+        }
+    }
+    let mut sign = sign.substitute(&vars);
+    // over a sequence, the result is a lazy sequence, see vm::built_ins::lazy_kind
+    if let (Expr::Symbol(Symbol(_, name)), Some(iterable)) = (callee.as_ref(), args.last()) {
+        let iterable_type = type_idx.get(&iterable.get_id());
+        if let (Some(_), Some(DataType::HeapType(HeapType::Seq(_)))) =
+            (vm::built_ins::lazy_kind(name), iterable_type)
+        {
+            if let Some(DataType::HeapType(HeapType::Vec(elem))) = sign.result.concrete_type() {
+                sign.result = DataType::HeapType(HeapType::Seq(elem.clone())).into();
+            }
+        }
+    }
+    let Some(result) = sign.result.concrete_type().filter(|t| !t.is_generic()) else {
+        return Err(Error::UnknownType(*id));
+    };
+    type_idx.insert(EnvironmentIdentifier::AstId(*id), result.clone());
+    type_idx.insert(
+        callee.get_id(),
+        DataType::Callable(CallableType::Builtin, Box::new(sign)),
+    );
+    Ok((env, type_idx))
+}
+
+/// The result of `xs[i]` depends on the type of the index: an int gives an element of the list,
+/// a sequence of ints, like a range, a list of elements
+fn infer_index_call(
+    call: &Call,
+    sign: &Signature,
+    env: Environment,
+    mut type_idx: TypeIndex,
+) -> Result<(Environment, TypeIndex)> {
+    let Call { id, callee, args } = call;
+    for arg in args {
+        (_, type_idx) = arg.infer_types(env.clone(), type_idx)?;
+    }
+    let list_type = type_idx.get(&args[0].get_id()).unwrap().clone();
+    let index_type = type_idx.get(&args[1].get_id()).unwrap().clone();
+    let DataType::HeapType(HeapType::Vec(elem_type)) = &list_type else {
+        return Err(Error::TypeMismatch(
+            args[0].get_id().into(),
+            sign.args[0].clone(),
+            list_type,
+        ));
+    };
+    let result = if index_type == DataType::int() {
+        elem_type.as_ref().clone()
+    } else if index_type == DataType::int().seq() {
+        list_type.clone()
+    } else {
+        return Err(Error::TypeMismatch(
+            args[1].get_id().into(),
+            sign.args[1].clone(),
+            index_type,
+        ));
+    };
+    type_idx.insert(EnvironmentIdentifier::AstId(*id), result.clone());
+    let sign = Signature::new()
+        .args(vec![list_type.into(), index_type.into()])
+        .result(result.into());
+    type_idx.insert(
+        callee.get_id(),
+        DataType::Callable(CallableType::Builtin, Box::new(sign)),
+    );
+    Ok((env, type_idx))
+}
+
+/// infers the body of a fragment that is passed to a generic built in, with the arguments the
+/// built in passes to it
+fn infer_passed_fragment(
+    fragment: &FnFragment,
+    sign: &Signature,
+    vars: &mut TypeVars,
+    env: &Environment,
+    mut type_idx: TypeIndex,
+) -> Result<TypeIndex> {
+    if fragment.args.len() != sign.args.len() {
+        return Err(Error::FragmentArgCount(fragment.id, sign.args.len()));
+    }
+    let mut env_for_body = env.clone();
+    let mut arg_types = vec![];
+    for (arg, t) in fragment.args.iter().zip(&sign.args) {
+        let t = t.substitute(vars);
+        let Some(t) = t.concrete_type().filter(|t| !t.is_generic()) else {
+            return Err(Error::UnknownType(arg.id));
+        };
+        type_idx.insert(arg.get_id(), t.clone());
+        env_for_body.add_entry(arg.name.clone(), arg.get_id());
+        arg_types.push(t.clone().into());
+    }
+    (_, type_idx) = fragment.body.infer_types(env_for_body, type_idx)?;
+    let body_type = type_idx.get(&fragment.body.get_id()).unwrap().clone();
+    if let Some(result) = sign.result.concrete_type().filter(|t| t.is_generic()) {
+        if !result.unify(&body_type, vars) {
+            return Err(Error::TypeMismatch(
+                fragment.body.get_id().into(),
+                result.substitute(vars).into(),
+                body_type,
+            ));
+        }
+    }
+    let my_type = DataType::fragment(Signature::new().args(arg_types).result(body_type.into()));
+    type_idx.insert(fragment.get_id(), my_type);
+    Ok(type_idx)
+}
+
 impl TypeInferable for IntLit {
     fn infer_types(
         &self,
@@ -244,6 +492,17 @@ impl TypeInferable for StrLit {
     }
 }
 
+impl TypeInferable for RegexLit {
+    fn infer_types(
+        &self,
+        env: Environment,
+        mut type_idx: TypeIndex,
+    ) -> Result<(Environment, TypeIndex)> {
+        type_idx.insert(EnvironmentIdentifier::AstId(self.0), DataType::regex());
+        Ok((env, type_idx))
+    }
+}
+
 impl TypeInferable for Let {
     fn infer_types(
         &self,
@@ -264,6 +523,65 @@ impl TypeInferable for Let {
     }
 }
 
+impl TypeInferable for Main {
+    fn infer_types(
+        &self,
+        env: Environment,
+        mut type_idx: TypeIndex,
+    ) -> Result<(Environment, TypeIndex)> {
+        let Main {
+            id,
+            spec,
+            bindings,
+            body,
+        } = self;
+        let mut env_for_body = env.clone();
+        for (binding, (_, t)) in bindings.iter().zip(spec.bindings()) {
+            type_idx.insert(binding.get_id(), t);
+            env_for_body.add_entry(binding.name.clone(), binding.get_id());
+        }
+        (_, type_idx) = body.infer_types(env_for_body, type_idx)?;
+        copy_type_info(
+            &mut type_idx,
+            &body.get_id(),
+            EnvironmentIdentifier::AstId(*id),
+        );
+        Ok((env, type_idx))
+    }
+}
+
+impl TypeInferable for Modes {
+    fn infer_types(
+        &self,
+        env: Environment,
+        mut type_idx: TypeIndex,
+    ) -> Result<(Environment, TypeIndex)> {
+        for mode in &self.modes {
+            (_, type_idx) = mode.infer_types(env.clone(), type_idx)?;
+        }
+        // the modes have different result types, so there is no result
+        type_idx.insert(EnvironmentIdentifier::AstId(self.id), DataType::unit());
+        Ok((env, type_idx))
+    }
+}
+
+impl TypeInferable for Splice {
+    fn infer_types(
+        &self,
+        env: Environment,
+        mut type_idx: TypeIndex,
+    ) -> Result<(Environment, TypeIndex)> {
+        let Splice { id, list } = self;
+        (_, type_idx) = list.infer_types(env.clone(), type_idx)?;
+        copy_type_info(
+            &mut type_idx,
+            &list.get_id(),
+            EnvironmentIdentifier::AstId(*id),
+        );
+        Ok((env, type_idx))
+    }
+}
+
 impl TypeInferable for Symbol {
     fn infer_types(
         &self,
@@ -280,7 +598,7 @@ impl TypeInferable for Symbol {
     }
 }
 
-/// generates and environment and a type index that already contains the built ins
+/// generates and environment and a type index that already contains the built ins and the globals
 pub fn inference_start() -> (Environment, TypeIndex) {
     let mut env = Scopes::default();
     let mut type_idx = TypeIndex::default();
@@ -297,6 +615,11 @@ pub fn inference_start() -> (Environment, TypeIndex) {
             ),
         );
     }
+    for (i, global) in vm::GLOBALS.iter().enumerate() {
+        let id = EnvironmentIdentifier::Global(i);
+        env.add_entry(global.to_string(), id);
+        type_idx.insert(id, vm::global_type(global).unwrap());
+    }
     (env, type_idx)
 }
 
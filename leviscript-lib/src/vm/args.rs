@@ -0,0 +1,350 @@
+//! Parses the arguments of a script according to an [ArgSpec], and generates its help text
+
+use crate::core::*;
+use crate::vm::Storable;
+
+use std::collections::HashMap;
+
+/// Why parsing didn't produce values
+#[derive(Debug, PartialEq)]
+pub enum ArgsError {
+    /// --help was passed
+    Help,
+    /// The arguments don't fit the spec, contains the message for the user
+    Usage(String),
+}
+
+/// Returns the values for [ArgSpec::bindings], in the same order. `args` doesn't contain the
+/// name of the script
+pub fn parse(spec: &ArgSpec, args: &[String]) -> Result<Vec<Storable>, ArgsError> {
+    let mut option_values: HashMap<usize, String> = HashMap::new();
+    let mut flag_counts = vec![0; spec.flags.len()];
+    let mut positionals = vec![];
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        if arg == "--" {
+            positionals.extend(args.by_ref().cloned());
+        } else if !arg.starts_with('-') || arg == "-" {
+            positionals.push(arg.clone());
+        } else if let Some(i) = find_named(&spec.flags, arg, |f| &f.names) {
+            flag_counts[i] += 1;
+        } else {
+            // --long=value
+            let (name, inline_value) = match arg.split_once('=') {
+                Some((name, value)) if name.starts_with("--") => (name, Some(value.to_string())),
+                _ => (arg.as_str(), None),
+            };
+            if let Some(i) = find_named(&spec.options, name, |o| &o.names) {
+                let value = match inline_value {
+                    Some(v) => v,
+                    None => args
+                        .next()
+                        .cloned()
+                        .ok_or_else(|| usage_err(format!("{} needs a value", name)))?,
+                };
+                option_values.insert(i, value);
+            } else if is_help(&spec.options, &spec.flags, arg) {
+                return Err(ArgsError::Help);
+            } else if let Some(indices) = split_short_flags(spec, arg) {
+                // combined short flags, e.g. -vvq
+                for i in indices {
+                    flag_counts[i] += 1;
+                }
+            } else {
+                return Err(usage_err(format!("unknown option: {}", arg)));
+            }
+        }
+    }
+
+    let mut res = vec![];
+    for (i, option) in spec.options.iter().enumerate() {
+        let name = display_name(&option.names);
+        let value = option_values
+            .remove(&i)
+            .or_else(|| option.default.clone())
+            .ok_or_else(|| usage_err(format!("{} is required", name)))?;
+        res.push(match option.arg_type {
+            ArgType::Str => value.into(),
+            ArgType::Int => parse_int(&value)
+                .ok_or_else(|| usage_err(format!("{} needs an int, found '{}'", name, value)))?
+                .into(),
+        });
+    }
+    res.extend(flag_counts.into_iter().map(|c| (c as i64).into()));
+    match &spec.positionals {
+        Positionals::List(_) => res.push(positionals.into()),
+        Positionals::Names { names, rest: None } => {
+            if names.len() != positionals.len() {
+                return Err(usage_err(format!(
+                    "expected {} arguments, found {}",
+                    names.len(),
+                    positionals.len()
+                )));
+            }
+            res.extend(positionals.into_iter().map(Storable::from));
+        }
+        Positionals::Names {
+            names,
+            rest: Some(rest_idx),
+        } => {
+            let n_singles = names.len() - 1;
+            if positionals.len() < n_singles {
+                return Err(usage_err(format!(
+                    "expected at least {} arguments, found {}",
+                    n_singles,
+                    positionals.len()
+                )));
+            }
+            // the rest element takes what the names after it leave over
+            let rest_end = positionals.len() - (n_singles - rest_idx);
+            let mut positionals = positionals.into_iter();
+            res.extend(positionals.by_ref().take(*rest_idx).map(Storable::from));
+            res.push(
+                positionals
+                    .by_ref()
+                    .take(rest_end - rest_idx)
+                    .collect::<Vec<_>>()
+                    .into(),
+            );
+            res.extend(positionals.map(Storable::from));
+        }
+    }
+    Ok(res)
+}
+
+/// Accepts the same formats as int literals: an optional minus, then a digit, or 0x and a hex
+/// digit, followed by more digits or `_`
+pub fn parse_int(s: &str) -> Option<i64> {
+    let (sign, s) = match s.strip_prefix('-') {
+        Some(rest) => ("-", rest),
+        None => ("", s),
+    };
+    let (digits, radix) = match s.strip_prefix("0x") {
+        Some(hex) => (hex, 16),
+        None => (s, 10),
+    };
+    let starts_with_digit = digits.chars().next().is_some_and(|c| c.is_digit(radix));
+    if !starts_with_digit || !digits.chars().all(|c| c == '_' || c.is_digit(radix)) {
+        return None;
+    }
+    // with the sign, so that the smallest int can be parsed too
+    i64::from_str_radix(&format!("{}{}", sign, digits.replace('_', "")), radix).ok()
+}
+
+/// `usage: name [options] positionals`
+pub fn usage(spec: &ArgSpec, script_name: &str) -> String {
+    let mut res = format!("usage: {}", script_name);
+    if !spec.options.is_empty() || !spec.flags.is_empty() {
+        res.push_str(" [options]");
+    }
+    match &spec.positionals {
+        Positionals::List(name) => res.push_str(&format!(" [{}...]", name)),
+        Positionals::Names { names, rest } => {
+            for (i, name) in names.iter().enumerate() {
+                if Some(i) == *rest {
+                    res.push_str(&format!(" [{}...]", name));
+                } else {
+                    res.push_str(&format!(" <{}>", name));
+                }
+            }
+        }
+    }
+    res
+}
+
+/// The usage line, the help string of the spec, and a table of options and flags
+pub fn help_text(spec: &ArgSpec, script_name: &str) -> String {
+    let mut res = usage(spec, script_name);
+    res.push('\n');
+    if let Some(help) = &spec.help {
+        res.push_str(&format!("\n{}\n", help));
+    }
+    res.push_str(&tables(vec![
+        ("options", option_rows(&spec.options)),
+        ("flags", flag_rows(&spec.options, &spec.flags)),
+    ]));
+    res
+}
+
+/// Returns the index of the mode that is selected by the first positional argument, and the
+/// position of that argument. The shared options and flags may come before the mode, e.g.
+/// `script --env prod apply`, they are parsed together with the arguments of the mode
+pub fn select_mode(spec: &ModesSpec, args: &[String]) -> Result<(usize, usize), ArgsError> {
+    let names: Vec<_> = spec.modes.iter().map(|(name, _)| name.as_str()).collect();
+    let mut pos = 0;
+    while let Some(arg) = args.get(pos) {
+        if !arg.starts_with('-') || arg == "-" {
+            break;
+        }
+        if arg == "--" {
+            // the mode follows
+            pos += 1;
+            break;
+        }
+        if is_help(&spec.options, &spec.flags, arg) {
+            return Err(ArgsError::Help);
+        }
+        if find_named(&spec.flags, arg, |f| &f.names).is_some() {
+            pos += 1;
+        } else if let Some(i) = find_named(&spec.options, arg, |o| &o.names) {
+            // the value follows the option
+            if pos + 1 == args.len() {
+                let name = display_name(&spec.options[i].names);
+                return Err(usage_err(format!("{} needs a value", name)));
+            }
+            pos += 2;
+        } else if arg.starts_with("--") && arg.contains('=') {
+            // --long=value, it's checked once the mode is known
+            pos += 1;
+        } else {
+            return Err(usage_err(format!("unknown option: {}", arg)));
+        }
+    }
+    let Some(arg) = args.get(pos) else {
+        return Err(usage_err(format!(
+            "a mode is required, available modes: {}",
+            names.join(", ")
+        )));
+    };
+    let mode = names.iter().position(|n| n == arg).ok_or_else(|| {
+        usage_err(format!(
+            "unknown mode '{}', available modes: {}",
+            arg,
+            names.join(", ")
+        ))
+    })?;
+    Ok((mode, pos))
+}
+
+/// `usage: name <mode> [options] ...`
+pub fn modes_usage(spec: &ModesSpec, script_name: &str) -> String {
+    let mut res = format!("usage: {} <mode>", script_name);
+    if !spec.options.is_empty() || !spec.flags.is_empty() {
+        res.push_str(" [options]");
+    }
+    res.push_str(" ...");
+    res
+}
+
+/// The usage line, the help of the all section, the modes with their short help and the shared
+/// options and flags
+pub fn modes_help_text(spec: &ModesSpec, script_name: &str) -> String {
+    let mut res = modes_usage(spec, script_name);
+    res.push('\n');
+    if let Some(help) = &spec.help {
+        res.push_str(&format!("\n{}\n", help));
+    }
+    let modes = spec
+        .modes
+        .iter()
+        .map(|(name, help)| (name.clone(), help.clone().unwrap_or_default()))
+        .collect();
+    res.push_str(&tables(vec![
+        ("modes", modes),
+        ("options", option_rows(&spec.options)),
+        ("flags", flag_rows(&spec.options, &spec.flags)),
+    ]));
+    res
+}
+
+fn option_rows(options: &[OptionSpec]) -> Vec<(String, String)> {
+    options
+        .iter()
+        .map(|o| {
+            let arg_type = match o.arg_type {
+                ArgType::Str => "str",
+                ArgType::Int => "int",
+            };
+            let mut help = o.help.clone().unwrap_or_default();
+            if let Some(default) = &o.default {
+                help = format!("{} (default: {})", help, default).trim().into();
+            }
+            (format!("{} <{}>", names_column(&o.names), arg_type), help)
+        })
+        .collect()
+}
+
+/// also contains the help flag
+fn flag_rows(options: &[OptionSpec], flags: &[FlagSpec]) -> Vec<(String, String)> {
+    let mut rows: Vec<_> = flags
+        .iter()
+        .map(|f| (names_column(&f.names), f.help.clone().unwrap_or_default()))
+        .collect();
+    if is_help(options, flags, "-h") {
+        rows.push(("-h, --help".into(), "print this help".into()));
+    } else {
+        rows.push(("    --help".into(), "print this help".into()));
+    }
+    rows
+}
+
+/// renders titled tables of names and their help. All tables share the width of the name column
+fn tables(tables: Vec<(&str, Vec<(String, String)>)>) -> String {
+    let width = tables
+        .iter()
+        .flat_map(|(_, rows)| rows)
+        .map(|(n, _)| n.len())
+        .max()
+        .unwrap_or(0);
+    let mut res = String::new();
+    for (title, rows) in tables {
+        if rows.is_empty() {
+            continue;
+        }
+        res.push_str(&format!("\n{}:\n", title));
+        for (names, help) in rows {
+            let mut lines = help.lines();
+            let first = lines.next().unwrap_or_default();
+            res.push_str(format!("  {:width$}  {}", names, first).trim_end());
+            res.push('\n');
+            for line in lines {
+                res.push_str(format!("  {:width$}  {}", "", line).trim_end());
+                res.push('\n');
+            }
+        }
+    }
+    res
+}
+
+fn usage_err(msg: String) -> ArgsError {
+    ArgsError::Usage(msg)
+}
+
+fn find_named<T>(items: &[T], arg: &str, names: impl Fn(&T) -> &ArgNames) -> Option<usize> {
+    items.iter().position(|i| names(i).matches(arg))
+}
+
+/// --help always works, -h only if no flag or option uses it
+fn is_help(options: &[OptionSpec], flags: &[FlagSpec], arg: &str) -> bool {
+    arg == "--help"
+        || (arg == "-h"
+            && find_named(flags, arg, |f| &f.names).is_none()
+            && find_named(options, arg, |o| &o.names).is_none())
+}
+
+/// returns the indices of the flags, if the arg consists only of one-letter short flags
+fn split_short_flags(spec: &ArgSpec, arg: &str) -> Option<Vec<usize>> {
+    let letters = arg.strip_prefix('-')?;
+    if letters.starts_with('-') {
+        return None;
+    }
+    letters
+        .chars()
+        .map(|c| find_named(&spec.flags, &format!("-{}", c), |f| &f.names))
+        .collect()
+}
+
+fn display_name(names: &ArgNames) -> &str {
+    names.long.as_ref().or(names.short.as_ref()).unwrap()
+}
+
+/// short names are aligned in their own column
+fn names_column(names: &ArgNames) -> String {
+    match (&names.short, &names.long) {
+        (Some(s), Some(l)) => format!("{}, {}", s, l),
+        (Some(s), None) => s.clone(),
+        (None, Some(l)) => format!("    {}", l),
+        (None, None) => unreachable!(),
+    }
+}
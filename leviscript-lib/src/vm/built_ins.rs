@@ -13,32 +13,1313 @@ use crate::core::*;
 use crate::vm::*;
 
 use std::any::type_name;
+use std::borrow::Cow;
+use std::ffi::{OsStr, OsString};
 use std::fmt::Debug;
+use std::fs;
+use std::io::{IsTerminal, Read, Write};
+use std::os::fd::AsFd;
+use std::os::unix::process::ExitStatusExt;
+use std::path::{Path, PathBuf};
 use std::process;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use chrono::format::{Item, StrftimeItems};
+use chrono::{DateTime, Local, NaiveDate, NaiveDateTime, NaiveTime, TimeZone, Utc};
+use crossterm::style::{Color, StyledContent, Stylize};
 
 pub mod impls {
     use super::*;
 
-    pub unsafe fn exec(bin_name: *const String, args: Vec<*const String>) -> Result<()> {
-        let stat = process::Command::new(&*bin_name)
-            .args(args.iter().map(|a| &**a))
-            .status()
-            .map_err(|e| rt_err!("Executing {}: {}", &*bin_name, e))?;
-        rt_assert!(
-            stat.success(),
-            "{} did not execute successfully",
-            &*bin_name
-        );
-        Ok(())
+    pub unsafe fn exec(bin_name: *const String, args: Vec<*const String>) -> Result<()> {
+        run(command(&*bin_name, &args), &*bin_name)
+    }
+
+    /// exec with redirected streams, see [redirect]
+    pub unsafe fn exec_redirected(
+        stdin: AnyData,
+        stdout: AnyData,
+        stderr: AnyData,
+        bin_name: *const String,
+        args: Vec<*const String>,
+    ) -> Result<()> {
+        let mut cmd = command(&*bin_name, &args);
+        redirect(&mut cmd, &stdin.0, &stdout.0, &stderr.0, false)?;
+        run(cmd, &*bin_name)
+    }
+
+    pub unsafe fn strcat(strs: Vec<*const String>) -> Result<String> {
+        let strs: Vec<&str> = strs.iter().map(|v| (&**v).as_ref()).collect();
+        Ok(strs.join(""))
+    }
+
+    /// the program shares the streams of the script, its output goes through
+    pub unsafe fn spawn(
+        mem: &mut Memory,
+        bin_name: *const String,
+        args: Vec<*const String>,
+    ) -> Result<ProcHandle> {
+        start_bg(mem, command(&*bin_name, &args), &*bin_name)
+    }
+
+    /// spawn with redirected streams, see [redirect]. Output redirected to CAPTURE is collected
+    /// for captured
+    pub unsafe fn spawn_redirected(
+        mem: &mut Memory,
+        stdin: AnyData,
+        stdout: AnyData,
+        stderr: AnyData,
+        bin_name: *const String,
+        args: Vec<*const String>,
+    ) -> Result<ProcHandle> {
+        let mut cmd = command(&*bin_name, &args);
+        redirect(&mut cmd, &stdin.0, &stdout.0, &stderr.0, true)?;
+        start_bg(mem, cmd, &*bin_name)
+    }
+
+    pub unsafe fn is_running(mem: &mut Memory, handle: ProcHandle) -> Result<bool> {
+        let status = mem.children[handle.idx]
+            .child
+            .try_wait()
+            .map_err(|e| rt_err!("Querying process {}: {}", handle.pid, e))?;
+        Ok(status.is_none())
+    }
+
+    /// blocks until the process finished, and returns its exit code. If the process was
+    /// terminated by a signal, the code is 128 + the signal number, like in bash
+    pub unsafe fn wait(mem: &mut Memory, handle: ProcHandle) -> Result<i64> {
+        let status = mem.children[handle.idx]
+            .child
+            .wait()
+            .map_err(|e| rt_err!("Waiting for process {}: {}", handle.pid, e))?;
+        match (status.code(), status.signal()) {
+            (Some(code), _) => Ok(code as i64),
+            (None, Some(signal)) => Ok(128 + signal as i64),
+            (None, None) => Err(rt_err!("process {} has no exit status", handle.pid)),
+        }
+    }
+
+    /// the stdout and stderr the process wrote to CAPTURE, the streams that weren't captured
+    /// are empty. Blocks until the process closed them, which usually happens when it ends
+    pub unsafe fn captured(mem: &mut Memory, handle: ProcHandle) -> Result<Storable> {
+        let (stdout, stderr) = mem.children[handle.idx].output();
+        Ok(Storable::VecOnHeap(vec![stdout.into(), stderr.into()]))
+    }
+
+    pub unsafe fn kill(mem: &mut Memory, handle: ProcHandle, signal: i64) -> Result<()> {
+        // a process that already finished, but was not reaped yet, still exists as zombie, so
+        // its pid can't have been reused. Once it's reaped, there is nothing to kill anymore
+        if let Ok(Some(_)) = mem.children[handle.idx].child.try_wait() {
+            return Ok(());
+        }
+        let res = libc::kill(handle.pid as libc::pid_t, signal as libc::c_int);
+        rt_assert!(
+            res == 0,
+            "Sending signal {} to process {}: {}",
+            signal,
+            handle.pid,
+            std::io::Error::last_os_error()
+        );
+        Ok(())
+    }
+
+    pub unsafe fn glob(pattern: *const String) -> Result<Vec<String>> {
+        let paths = glob::glob(&*pattern).map_err(|e| rt_err!("Invalid glob: {}", e))?;
+        let mut matches = paths
+            .map(|p| {
+                let p = p.map_err(|e| rt_err!("Globbing {}: {}", &*pattern, e))?;
+                path_to_string(p)
+            })
+            .collect::<Result<Vec<_>>>()?;
+        matches.sort();
+        Ok(matches)
+    }
+
+    pub unsafe fn glob_strict(pattern: *const String) -> Result<Vec<String>> {
+        let matches = glob(pattern)?;
+        rt_assert!(!matches.is_empty(), "No matches for glob: {}", &*pattern);
+        Ok(matches)
+    }
+
+    pub unsafe fn escape_glob(s: *const String) -> Result<String> {
+        Ok(glob::Pattern::escape(&*s))
+    }
+
+    pub unsafe fn glob_match(s: *const String, pattern: *const String) -> Result<bool> {
+        let pattern = glob::Pattern::new(&*pattern).map_err(|e| rt_err!("Invalid glob: {}", e))?;
+        Ok(pattern.matches(&*s))
+    }
+
+    /// reads everything that is left in the stream
+    pub unsafe fn read(stream: Stream) -> Result<String> {
+        if stream == Stream::Null {
+            return Ok(String::new());
+        }
+        rt_assert!(stream == Stream::In, "Can't read from {}", stream);
+        let mut res = String::new();
+        std::io::stdin()
+            .read_to_string(&mut res)
+            .map_err(|e| rt_err!("Reading from {}: {}", stream, e))?;
+        Ok(res)
+    }
+
+    pub unsafe fn write(stream: Stream, s: *const String) -> Result<()> {
+        write_to(stream, &*s)
+    }
+
+    /// broken symlinks don't exist
+    pub unsafe fn exists(path: *const String) -> Result<bool> {
+        Path::new(&*path)
+            .try_exists()
+            .map_err(|e| rt_err!("Checking whether {} exists: {}", &*path, e))
+    }
+
+    pub unsafe fn is_dir(path: *const String) -> Result<bool> {
+        Ok(Path::new(&*path).is_dir())
+    }
+
+    pub unsafe fn is_file(path: *const String) -> Result<bool> {
+        Ok(Path::new(&*path).is_file())
+    }
+
+    pub unsafe fn is_relative(path: *const String) -> Result<bool> {
+        Ok(Path::new(&*path).is_relative())
+    }
+
+    /// the last component, or an empty str if the path ends in `..` or is the root
+    pub unsafe fn name(path: *const String) -> Result<String> {
+        Ok(os_str_part(Path::new(&*path).file_name()))
+    }
+
+    /// the name without its extension
+    pub unsafe fn stem(path: *const String) -> Result<String> {
+        Ok(os_str_part(Path::new(&*path).file_stem()))
+    }
+
+    /// the part of the name after the last dot, without the dot. Empty if there is none
+    pub unsafe fn extension(path: *const String) -> Result<String> {
+        Ok(os_str_part(Path::new(&*path).extension()))
+    }
+
+    /// the path without its last component. For the root, or a path without parent, it's empty
+    pub unsafe fn parent(path: *const String) -> Result<String> {
+        Ok(os_str_part(Path::new(&*path).parent().map(Path::as_os_str)))
+    }
+
+    /// implements `/`. If the second path is absolute, it replaces the first one
+    pub unsafe fn join_path(base: *const String, path: *const String) -> Result<String> {
+        path_to_string(Path::new(&*base).join(&*path))
+    }
+
+    /// the absolute path, with all symlinks, `.` and `..` resolved. The path needs to exist
+    pub unsafe fn canonicalize(path: *const String) -> Result<String> {
+        let canonical =
+            fs::canonicalize(&*path).map_err(|e| rt_err!("Canonicalizing {}: {}", &*path, e))?;
+        path_to_string(canonical)
+    }
+
+    /// replaces a leading `~` by the home directory of the user
+    pub unsafe fn expand_home(path: *const String) -> Result<String> {
+        let path = &*path;
+        let rest = match path.strip_prefix('~') {
+            Some(rest) if rest.is_empty() || rest.starts_with('/') => rest,
+            _ => return Ok(path.clone()),
+        };
+        let home = std::env::var_os("HOME").ok_or_else(|| rt_err!("HOME is not set"))?;
+        let home = path_to_string(home.into())?;
+        Ok(format!("{}{}", home, rest))
+    }
+
+    pub unsafe fn read_file(path: *const String) -> Result<String> {
+        fs::read_to_string(&*path).map_err(|e| rt_err!("Reading {}: {}", &*path, e))
+    }
+
+    /// the line endings are removed, a trailing newline doesn't produce an empty line
+    pub unsafe fn read_lines(path: *const String) -> Result<Vec<String>> {
+        Ok(read_file(path)?.lines().map(String::from).collect())
+    }
+
+    /// creates the file, or replaces its content
+    pub unsafe fn write_file(path: *const String, content: *const String) -> Result<()> {
+        fs::write(&*path, &*content).map_err(|e| rt_err!("Writing {}: {}", &*path, e))
+    }
+
+    /// creates the file, if it doesn't exist
+    pub unsafe fn append_file(path: *const String, content: *const String) -> Result<()> {
+        fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&*path)
+            .and_then(|mut f| f.write_all((&*content).as_bytes()))
+            .map_err(|e| rt_err!("Appending to {}: {}", &*path, e))
+    }
+
+    /// deletes a file or an empty directory
+    pub unsafe fn delete(path: *const String) -> Result<()> {
+        let path = &*path;
+        let res = match fs::symlink_metadata(path) {
+            Ok(meta) if meta.is_dir() => fs::remove_dir(path),
+            _ => fs::remove_file(path),
+        };
+        res.map_err(|e| rt_err!("Deleting {}: {}", path, e))
+    }
+
+    /// copies a file, the target is replaced if it exists
+    pub unsafe fn copy(from: *const String, to: *const String) -> Result<()> {
+        fs::copy(&*from, &*to)
+            .map(|_| ())
+            .map_err(|e| rt_err!("Copying {} to {}: {}", &*from, &*to, e))
+    }
+
+    /// moves a file or directory. Files can be moved to another file system too
+    pub unsafe fn move_path(from: *const String, to: *const String) -> Result<()> {
+        let res = match fs::rename(&*from, &*to) {
+            Err(e) if e.raw_os_error() == Some(libc::EXDEV) && Path::new(&*from).is_file() => {
+                fs::copy(&*from, &*to).and_then(|_| fs::remove_file(&*from))
+            }
+            res => res,
+        };
+        res.map_err(|e| rt_err!("Moving {} to {}: {}", &*from, &*to, e))
+    }
+
+    /// creates the missing parents too. An existing directory is fine
+    pub unsafe fn make_dir(path: *const String) -> Result<()> {
+        fs::create_dir_all(&*path).map_err(|e| rt_err!("Creating directory {}: {}", &*path, e))
+    }
+
+    /// an empty separator splits into the chars
+    pub unsafe fn split(s: *const String, sep: *const String) -> Result<Vec<String>> {
+        let (s, sep) = (&*s, &*sep);
+        Ok(if sep.is_empty() {
+            s.chars().map(String::from).collect()
+        } else {
+            s.split(sep.as_str()).map(String::from).collect()
+        })
+    }
+
+    /// splits at \n and \r\n, a trailing line ending doesn't produce an empty line
+    pub unsafe fn lines(s: *const String) -> Result<Vec<String>> {
+        Ok((&*s).lines().map(String::from).collect())
+    }
+
+    pub unsafe fn join(strs: Vec<*const String>, sep: *const String) -> Result<String> {
+        let strs: Vec<&str> = strs.iter().map(|s| (**s).as_str()).collect();
+        Ok(strs.join(&*sep))
+    }
+
+    pub unsafe fn trim(s: *const String) -> Result<String> {
+        Ok((&*s).trim().into())
+    }
+
+    pub unsafe fn trim_start(s: *const String) -> Result<String> {
+        Ok((&*s).trim_start().into())
+    }
+
+    pub unsafe fn trim_end(s: *const String) -> Result<String> {
+        Ok((&*s).trim_end().into())
+    }
+
+    /// replaces all occurrences
+    pub unsafe fn replace(
+        s: *const String,
+        from: *const String,
+        to: *const String,
+    ) -> Result<String> {
+        rt_assert!(!(&*from).is_empty(), "Can't replace an empty str");
+        Ok((&*s).replace(&*from, &*to))
+    }
+
+    pub unsafe fn starts_with(s: *const String, prefix: *const String) -> Result<bool> {
+        Ok((&*s).starts_with(&*prefix))
+    }
+
+    pub unsafe fn ends_with(s: *const String, suffix: *const String) -> Result<bool> {
+        Ok((&*s).ends_with(&*suffix))
+    }
+
+    pub unsafe fn contains(s: *const String, part: *const String) -> Result<bool> {
+        Ok((&*s).contains(&*part))
+    }
+
+    pub unsafe fn upper(s: *const String) -> Result<String> {
+        Ok((&*s).to_uppercase())
+    }
+
+    pub unsafe fn lower(s: *const String) -> Result<String> {
+        Ok((&*s).to_lowercase())
+    }
+
+    /// the number of chars, not bytes
+    pub unsafe fn len(s: *const String) -> Result<i64> {
+        Ok((&*s).chars().count() as i64)
+    }
+
+    pub unsafe fn byte_len(s: *const String) -> Result<i64> {
+        Ok((&*s).len() as i64)
+    }
+
+    /// the chars from start up to, but not including, end
+    pub unsafe fn slice(s: *const String, start: i64, end: i64) -> Result<String> {
+        let s = &*s;
+        let len = s.chars().count();
+        let (start, end) = slice_range(start, end, len)?;
+        Ok(s.chars().skip(start).take(end - start).collect())
+    }
+
+    /// like slice, but the indices count bytes. They must not be inside of a char
+    pub unsafe fn byte_slice(s: *const String, start: i64, end: i64) -> Result<String> {
+        let s = &*s;
+        let (start, end) = slice_range(start, end, s.len())?;
+        s.get(start..end)
+            .map(String::from)
+            .ok_or_else(|| rt_err!("The range {}..{} splits a char of: {}", start, end, s))
+    }
+
+    /// fails if the pattern is invalid
+    pub unsafe fn regex(mem: &mut Memory, pattern: *const String) -> Result<Storable> {
+        mem.regex(&*pattern)?;
+        Ok(Storable::OnHeap(Value::Regex((&*pattern).clone())))
+    }
+
+    /// whether the regex matches anywhere in the str
+    pub unsafe fn matches(mem: &mut Memory, re: *const String, s: *const String) -> Result<bool> {
+        Ok(mem.regex(&*re)?.is_match(&*s))
+    }
+
+    /// the first match, followed by its groups. Groups that didn't participate are empty. If
+    /// there is no match, the list is empty
+    pub unsafe fn find(
+        mem: &mut Memory,
+        re: *const String,
+        s: *const String,
+    ) -> Result<Vec<String>> {
+        let re = mem.regex(&*re)?;
+        Ok(re
+            .captures(&*s)
+            .map(|caps| capture_list(&caps))
+            .unwrap_or_default())
+    }
+
+    /// all matches, each one like the result of find
+    pub unsafe fn find_all(
+        mem: &mut Memory,
+        re: *const String,
+        s: *const String,
+    ) -> Result<Vec<Vec<String>>> {
+        let re = mem.regex(&*re)?;
+        Ok(re
+            .captures_iter(&*s)
+            .map(|caps| capture_list(&caps))
+            .collect())
+    }
+
+    /// the named groups of the first match that participated in it
+    pub unsafe fn captures(
+        mem: &mut Memory,
+        re: *const String,
+        s: *const String,
+    ) -> Result<Vec<(String, String)>> {
+        let re = mem.regex(&*re)?;
+        let Some(caps) = re.captures(&*s) else {
+            return Ok(vec![]);
+        };
+        Ok(re
+            .capture_names()
+            .flatten()
+            .filter_map(|name| Some((name.into(), caps.name(name)?.as_str().into())))
+            .collect())
+    }
+
+    /// the replacement can refer to groups with $1 or ${name}
+    pub unsafe fn replace_all(
+        mem: &mut Memory,
+        re: *const String,
+        s: *const String,
+        replacement: *const String,
+    ) -> Result<String> {
+        let re = mem.regex(&*re)?;
+        Ok(re.replace_all(&*s, (&*replacement).as_str()).into_owned())
+    }
+
+    /// fails with the byte offset of the first error
+    pub unsafe fn from_json(s: *const String) -> Result<Storable> {
+        json::parse(&*s)
+    }
+
+    pub unsafe fn to_json(v: AnyData) -> Result<String> {
+        Ok(json::from_data(&v.0)?.to_string())
+    }
+
+    /// indented by two spaces
+    pub unsafe fn to_json_pretty(v: AnyData) -> Result<String> {
+        serde_json::to_string_pretty(&json::from_data(&v.0)?)
+            .map_err(|e| rt_err!("Converting to json: {}", e))
+    }
+
+    /// one of null, bool, int, float, str, list and dict
+    pub unsafe fn json_type(j: JsonData) -> Result<String> {
+        Ok(json::type_name(&j.0).into())
+    }
+
+    pub unsafe fn json_get(j: JsonData, key: *const String) -> Result<Storable> {
+        json::get(&j.0, &*key)
+    }
+
+    pub unsafe fn json_at(j: JsonData, idx: i64) -> Result<Storable> {
+        json::at(&j.0, idx)
+    }
+
+    pub unsafe fn json_keys(j: JsonData) -> Result<Vec<String>> {
+        json::keys(&j.0)
+    }
+
+    /// none if the json value isn't a list
+    pub unsafe fn json_list(j: JsonData) -> Result<Storable> {
+        if json::type_name(&j.0) != "list" {
+            return Ok(Storable::OptOnHeap(None));
+        }
+        Ok(Some(Storable::VecOnHeap(json::elems(&j.0)?)).into())
+    }
+
+    /// none if the json value isn't a str
+    pub unsafe fn json_str(j: JsonData) -> Result<Storable> {
+        let s: Option<*const String> = j.0.rtry_into();
+        Ok(s.map(|s| (*s).clone()).into())
+    }
+
+    /// none if the json value isn't an int
+    pub unsafe fn json_int(j: JsonData) -> Result<Storable> {
+        let i: Option<i64> = j.0.rtry_into();
+        Ok(i.into())
+    }
+
+    /// none if the json value isn't a number, ints are converted,
+    /// since json doesn't distinguish them
+    pub unsafe fn json_float(j: JsonData) -> Result<Storable> {
+        let f = match j.0 {
+            Data::CopyVal(CopyValue::Int(i)) => Some(i as f64),
+            Data::CopyVal(CopyValue::Float(f)) => Some(f.0),
+            _ => None,
+        };
+        Ok(f.into())
+    }
+
+    /// none if the json value isn't a bool
+    pub unsafe fn json_bool(j: JsonData) -> Result<Storable> {
+        let b: Option<bool> = j.0.rtry_into();
+        Ok(b.into())
+    }
+
+    /// none if the variable isn't set
+    pub unsafe fn get_env(name: *const String) -> Result<Storable> {
+        let value = std::env::var_os(&*name)
+            .map(|value| env_value(&*name, value))
+            .transpose()?;
+        Ok(value.into())
+    }
+
+    pub unsafe fn get_env_or(name: *const String, default: *const String) -> Result<String> {
+        match std::env::var_os(&*name) {
+            Some(value) => env_value(&*name, value),
+            None => Ok((*default).clone()),
+        }
+    }
+
+    pub unsafe fn has_env(name: *const String) -> Result<bool> {
+        Ok(std::env::var_os(&*name).is_some())
+    }
+
+    /// the variable is inherited by all programs that are started afterwards
+    pub unsafe fn set_env(name: *const String, value: *const String) -> Result<()> {
+        check_env_var(&*name, &*value)?;
+        std::env::set_var(&*name, &*value);
+        Ok(())
+    }
+
+    pub unsafe fn unset_env(name: *const String) -> Result<()> {
+        check_env_var(&*name, "")?;
+        std::env::remove_var(&*name);
+        Ok(())
+    }
+
+    /// sets the variable for the body, afterwards the previous value is restored
+    pub unsafe fn with_env(
+        mem: &mut Memory,
+        name: *const String,
+        value: *const String,
+    ) -> Result<()> {
+        check_env_var(&*name, &*value)?;
+        let old = std::env::var_os(&*name);
+        mem.scope_guards.push(ScopeGuard::Env((*name).clone(), old));
+        std::env::set_var(&*name, &*value);
+        Ok(())
+    }
+
+    /// changes the working directory for the body, afterwards the previous one is restored
+    pub unsafe fn with_cwd(mem: &mut Memory, dir: *const String) -> Result<()> {
+        let old =
+            std::env::current_dir().map_err(|e| rt_err!("Reading the working directory: {}", e))?;
+        std::env::set_current_dir(&*dir)
+            .map_err(|e| rt_err!("Changing directory to {}: {}", &*dir, e))?;
+        mem.scope_guards.push(ScopeGuard::Cwd(old));
+        Ok(())
+    }
+
+    /// creates an empty file in the temp dir, which is deleted after the body
+    pub unsafe fn with_tmp_path(mem: &mut Memory) -> Result<String> {
+        let path = create_tmp_path(|p| {
+            fs::File::options()
+                .write(true)
+                .create_new(true)
+                .open(p)
+                .map(|_| ())
+        })?;
+        mem.scope_guards
+            .push(ScopeGuard::TmpPath(path.clone().into()));
+        Ok(path)
+    }
+
+    /// creates an empty directory in the temp dir, which is deleted with its content after the
+    /// body
+    pub unsafe fn with_tmp_dir(mem: &mut Memory) -> Result<String> {
+        let path = create_tmp_path(|p| fs::create_dir(p))?;
+        mem.scope_guards
+            .push(ScopeGuard::TmpPath(path.clone().into()));
+        Ok(path)
+    }
+
+    pub unsafe fn print(values: Vec<AnyData>) -> Result<()> {
+        print_to(Stream::Out, values, "")
+    }
+
+    pub unsafe fn println(values: Vec<AnyData>) -> Result<()> {
+        print_to(Stream::Out, values, "\n")
+    }
+
+    pub unsafe fn eprint(values: Vec<AnyData>) -> Result<()> {
+        print_to(Stream::Err, values, "")
+    }
+
+    pub unsafe fn eprintln(values: Vec<AnyData>) -> Result<()> {
+        print_to(Stream::Err, values, "\n")
+    }
+
+    /// the line is returned without the line break. Fails at the end of the input
+    pub unsafe fn read_line() -> Result<String> {
+        read_stdin_line()?
+            .ok_or_else(|| rt_err!("Reading a line from {}: end of input", Stream::In))
+    }
+
+    pub unsafe fn read_all() -> Result<String> {
+        read(Stream::In)
+    }
+
+    /// the remaining lines, without their line breaks. Stdin is read while they are consumed
+    pub unsafe fn stdin_lines() -> Result<Storable> {
+        Ok(Storable::SeqOnHeap(Sequence::StdinLines))
+    }
+
+    /// the message is written to stderr, so it isn't captured with the output
+    pub unsafe fn prompt(msg: *const String) -> Result<String> {
+        ask(&*msg)
+    }
+
+    /// asks until the answer is yes or no. An empty answer is the default, which is "y" or "n"
+    pub unsafe fn confirm(msg: *const String, default: *const String) -> Result<bool> {
+        let (default, options) = match (&*default).as_str() {
+            "y" => (true, "[Y/n]"),
+            "n" => (false, "[y/N]"),
+            other => return Err(rt_err!("The default must be y or n, found: {}", other)),
+        };
+        loop {
+            match ask(&format!("{} {} ", &*msg, options))?
+                .trim()
+                .to_lowercase()
+                .as_str()
+            {
+                "" => return Ok(default),
+                "y" | "yes" => return Ok(true),
+                "n" | "no" => return Ok(false),
+                _ => continue,
+            }
+        }
+    }
+
+    /// like prompt, but the input isn't echoed
+    pub unsafe fn password(msg: *const String) -> Result<String> {
+        let mut term = std::mem::zeroed::<libc::termios>();
+        rt_assert!(
+            std::io::stdin().is_terminal() && libc::tcgetattr(libc::STDIN_FILENO, &mut term) == 0,
+            "Can't ask for input, {} is not a terminal",
+            Stream::In
+        );
+        let mut hidden = term;
+        hidden.c_lflag &= !libc::ECHO;
+        libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &hidden);
+        let res = ask(&*msg);
+        libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &term);
+        // the line break of the answer wasn't echoed either
+        write_to(Stream::Err, "\n")?;
+        res
+    }
+
+    /// the color is one of black, red, green, yellow, blue, magenta, cyan, white, grey, or a dark_
+    /// variant, e.g. dark_red
+    pub unsafe fn color(name: *const String, s: *const String) -> Result<String> {
+        Ok(styled((&*s).as_str().with(parse_color(&*name)?)))
+    }
+
+    pub unsafe fn bg_color(name: *const String, s: *const String) -> Result<String> {
+        Ok(styled((&*s).as_str().on(parse_color(&*name)?)))
+    }
+
+    pub unsafe fn bold(s: *const String) -> Result<String> {
+        Ok(styled((&*s).as_str().bold()))
+    }
+
+    pub unsafe fn dim(s: *const String) -> Result<String> {
+        Ok(styled((&*s).as_str().dim()))
+    }
+
+    pub unsafe fn italic(s: *const String) -> Result<String> {
+        Ok(styled((&*s).as_str().italic()))
+    }
+
+    pub unsafe fn underline(s: *const String) -> Result<String> {
+        Ok(styled((&*s).as_str().underlined()))
+    }
+
+    pub unsafe fn is_terminal(stream: Stream) -> Result<bool> {
+        Ok(stream_is_terminal(stream))
+    }
+
+    /// the number of columns of the terminal. If the output doesn't go to a terminal, it's the
+    /// COLUMNS variable, or 80
+    pub unsafe fn term_width() -> Result<i64> {
+        if std::io::stdout().is_terminal() {
+            if let Ok((cols, _)) = crossterm::terminal::size() {
+                return Ok(cols as i64);
+            }
+        }
+        Ok(std::env::var("COLUMNS")
+            .ok()
+            .and_then(|c| c.parse().ok())
+            .unwrap_or(80))
+    }
+
+    /// draws a progress bar on stderr, which is replaced by the next one. When done reaches
+    /// total, the line is finished. Nothing is drawn, if stderr isn't a terminal
+    pub unsafe fn progress(done: i64, total: i64, msg: *const String) -> Result<()> {
+        const WIDTH: i64 = 30;
+        rt_assert!(
+            total > 0,
+            "The total of a progress bar must be positive, found {}",
+            total
+        );
+        if !std::io::stderr().is_terminal() {
+            return Ok(());
+        }
+        let done = done.clamp(0, total);
+        let filled = (done * WIDTH / total) as usize;
+        let bar = format!(
+            "\r\x1b[2K[{}{}] {:>3}% {}",
+            "#".repeat(filled),
+            " ".repeat(WIDTH as usize - filled),
+            done * 100 / total,
+            &*msg
+        );
+        let end = if done == total { "\n" } else { "" };
+        write_to(Stream::Err, &(bar + end))
+    }
+
+    /// shows a spinner with the message on stderr, while the body runs. If stderr isn't a
+    /// terminal, only the message is written
+    pub unsafe fn with_spinner(mem: &mut Memory, msg: *const String) -> Result<()> {
+        const FRAMES: [char; 10] = ['⠋', '⠙', '⠹', '⠸', '⠼', '⠴', '⠦', '⠧', '⠇', '⠏'];
+        let msg = (*msg).clone();
+        if !std::io::stderr().is_terminal() {
+            mem.scope_guards.push(ScopeGuard::Nothing);
+            return write_to(Stream::Err, &(msg + "\n"));
+        }
+        let stop = Arc::new(AtomicBool::new(false));
+        let stop_thread = stop.clone();
+        let thread = std::thread::spawn(move || {
+            for frame in FRAMES.iter().cycle() {
+                if stop_thread.load(Ordering::Relaxed) {
+                    break;
+                }
+                let mut line = spinner_line();
+                if !line.paused {
+                    eprint!("\r\x1b[2K{} {}", frame, msg);
+                    line.drawn = true;
+                }
+                drop(line);
+                std::thread::sleep(std::time::Duration::from_millis(80));
+            }
+        });
+        mem.scope_guards.push(ScopeGuard::Spinner(stop, thread));
+        Ok(())
+    }
+
+    /// times are milliseconds since the unix epoch, durations are milliseconds
+    pub unsafe fn now() -> Result<i64> {
+        Ok(Utc::now().timestamp_millis())
+    }
+
+    /// the duration from the time until now
+    pub unsafe fn since(time: i64) -> Result<i64> {
+        Ok(Utc::now().timestamp_millis() - time)
+    }
+
+    /// adds a duration to a time, or two durations. Durations may be negative
+    pub unsafe fn add_time(time: i64, duration: i64) -> Result<i64> {
+        time.checked_add(duration)
+            .ok_or_else(|| rt_err!("Adding {}ms to {} overflows", duration, time))
+    }
+
+    /// the duration from the first time to the second one
+    pub unsafe fn time_between(from: i64, to: i64) -> Result<i64> {
+        to.checked_sub(from)
+            .ok_or_else(|| rt_err!("The duration from {} to {} overflows", from, to))
+    }
+
+    /// formats the time in the local time zone, the pattern is like the one of strftime, e.g.
+    /// %Y-%m-%d
+    pub unsafe fn format_time(time: i64, pattern: *const String) -> Result<String> {
+        format_in(&Local, time, &*pattern)
+    }
+
+    pub unsafe fn format_utc(time: i64, pattern: *const String) -> Result<String> {
+        format_in(&Utc, time, &*pattern)
+    }
+
+    /// the time is in the local time zone, unless the pattern contains one. A missing time of
+    /// day is midnight
+    pub unsafe fn parse_time(s: *const String, pattern: *const String) -> Result<i64> {
+        parse_in(&Local, &*s, &*pattern)
+    }
+
+    pub unsafe fn parse_utc(s: *const String, pattern: *const String) -> Result<i64> {
+        parse_in(&Utc, &*s, &*pattern)
+    }
+
+    /// parses durations like 1h30m, 1.5s or 200ms. The units are d, h, m, s and ms
+    pub unsafe fn duration(s: *const String) -> Result<i64> {
+        parse_duration(&*s).ok_or_else(|| rt_err!("Invalid duration: {}", &*s))
+    }
+
+    pub unsafe fn format_duration(ms: i64) -> Result<String> {
+        Ok(super::format_duration(ms))
+    }
+
+    /// stops early if a signal is received
+    pub unsafe fn sleep(ms: i64) -> Result<()> {
+        rt_assert!(ms >= 0, "Can't sleep for a negative duration: {}ms", ms);
+        let end = std::time::Instant::now() + std::time::Duration::from_millis(ms as u64);
+        loop {
+            signals::check()?;
+            let left = end.saturating_duration_since(std::time::Instant::now());
+            if left.is_zero() {
+                return Ok(());
+            }
+            std::thread::sleep(left.min(std::time::Duration::from_millis(50)));
+        }
+    }
+
+    /// the call evaluates to a pair of the body and how long it took, see
+    /// [OpCode::LeaveMeasure]. The result is the start time, so a fragment can find out how long
+    /// it's been running
+    pub unsafe fn measure(mem: &mut Memory) -> Result<i64> {
+        let start = std::time::Instant::now();
+        mem.scope_guards.push(ScopeGuard::Measure(start));
+        now()
+    }
+
+    /// accepts the same formats as int literals, surrounding whitespace is ignored. None if the
+    /// str isn't an int
+    pub unsafe fn parse_int(s: *const String) -> Result<Storable> {
+        Ok(args::parse_int((&*s).trim()).into())
+    }
+
+    pub unsafe fn parse_int_or(s: *const String, default: i64) -> Result<i64> {
+        Ok(args::parse_int((&*s).trim()).unwrap_or(default))
+    }
+
+    /// the radix is between 2 and 36, the digits may be separated by _
+    pub unsafe fn parse_int_radix(s: *const String, radix: i64) -> Result<Storable> {
+        rt_assert!((2..=36).contains(&radix), "Invalid radix: {}", radix);
+        Ok(
+            i64::from_str_radix(&(&*s).trim().replace('_', ""), radix as u32)
+                .ok()
+                .into(),
+        )
+    }
+
+    pub unsafe fn parse_float(s: *const String) -> Result<Storable> {
+        Ok(float((&*s).trim()).into())
+    }
+
+    pub unsafe fn parse_float_or(s: *const String, default: f64) -> Result<f64> {
+        Ok(float((&*s).trim()).unwrap_or(default))
+    }
+
+    fn float(s: &str) -> Option<f64> {
+        s.replace('_', "").parse().ok()
+    }
+
+    pub unsafe fn to_float(i: i64) -> Result<f64> {
+        Ok(i as f64)
+    }
+
+    /// rounds to the given number of decimals
+    pub unsafe fn fixed(x: f64, decimals: i64) -> Result<String> {
+        rt_assert!(decimals >= 0, "Negative number of decimals: {}", decimals);
+        Ok(format!("{:.*}", decimals as usize, x))
+    }
+
+    /// fills the str up to the width in chars, e.g. padLeft "7" 3 "0" is 007. Longer strs stay as
+    /// they are
+    pub unsafe fn pad_left(s: *const String, width: i64, fill: *const String) -> Result<String> {
+        Ok(padding(&*s, width, &*fill)? + &*s)
+    }
+
+    pub unsafe fn pad_right(s: *const String, width: i64, fill: *const String) -> Result<String> {
+        Ok((*s).clone() + &padding(&*s, width, &*fill)?)
+    }
+
+    /// e.g. 1.5 KiB, the units are powers of 1024
+    pub unsafe fn format_bytes(n: i64) -> Result<String> {
+        const UNITS: [&str; 6] = ["KiB", "MiB", "GiB", "TiB", "PiB", "EiB"];
+        if n.unsigned_abs() < 1024 {
+            return Ok(format!("{} B", n));
+        }
+        let mut size = n as f64;
+        let mut unit = "B";
+        for u in UNITS {
+            if size.abs() < 1024.0 {
+                break;
+            }
+            size /= 1024.0;
+            unit = u;
+        }
+        let size = format!("{:.1}", size);
+        Ok(format!("{} {}", size.trim_end_matches(".0"), unit))
+    }
+
+    /// numbers are sorted by value, strs and lists lexicographically
+    pub unsafe fn sort(list: AnyData) -> Result<Storable> {
+        let elems = iteration::expect_list(&list.0)?;
+        let mut keyed: Vec<_> = elems.iter().map(|e| (iteration::sort_key(e), e)).collect();
+        keyed.sort_by(|(a, _), (b, _)| a.cmp(b));
+        let sorted = keyed
+            .into_iter()
+            .map(|(_, e)| iteration::copy(e))
+            .collect::<Result<_>>()?;
+        Ok(Storable::VecOnHeap(sorted))
+    }
+
+    /// keeps the first occurrence of each element
+    pub unsafe fn unique(list: AnyData) -> Result<Storable> {
+        let elems = iteration::expect_list(&list.0)?;
+        let unique = iteration::unique(&elems)
+            .into_iter()
+            .map(|e| iteration::copy(e))
+            .collect::<Result<_>>()?;
+        Ok(Storable::VecOnHeap(unique))
+    }
+
+    /// pairs up the elements, the longer list is cut off
+    pub unsafe fn zip(a: AnyData, b: AnyData) -> Result<Storable> {
+        let a = iteration::expect_list(&a.0)?;
+        let b = iteration::expect_list(&b.0)?;
+        let pairs = a
+            .iter()
+            .zip(&b)
+            .map(|(a, b)| {
+                let pair = vec![iteration::copy(a)?, iteration::copy(b)?];
+                Ok(Storable::VecOnHeap(pair))
+            })
+            .collect::<Result<_>>()?;
+        Ok(Storable::VecOnHeap(pairs))
+    }
+
+    /// pairs up each element with its index
+    pub unsafe fn enumerate(list: AnyData) -> Result<Storable> {
+        let elems = iteration::expect_list(&list.0)?;
+        let pairs = elems
+            .iter()
+            .enumerate()
+            .map(|(i, e)| {
+                let pair = vec![(i as i64).into(), iteration::copy(e)?];
+                Ok(Storable::VecOnHeap(pair))
+            })
+            .collect::<Result<_>>()?;
+        Ok(Storable::VecOnHeap(pairs))
+    }
+
+    pub unsafe fn flatten(lists: AnyData) -> Result<Storable> {
+        let mut flat = vec![];
+        for list in iteration::expect_list(&lists.0)? {
+            for elem in iteration::expect_list(&list)? {
+                flat.push(iteration::copy(&elem)?);
+            }
+        }
+        Ok(Storable::VecOnHeap(flat))
+    }
+
+    /// the file is read while the lines are consumed
+    pub unsafe fn file_lines(path: *const String) -> Result<Storable> {
+        Ok(Storable::SeqOnHeap(Sequence::file_lines(&*path)?))
+    }
+
+    /// the lines the program writes to stdout. It fails once the output is consumed, if the
+    /// program didn't succeed
+    pub unsafe fn output_lines(
+        _: &mut Memory,
+        bin_name: *const String,
+        args: Vec<*const String>,
+    ) -> Result<Storable> {
+        let args: Vec<&str> = args.iter().map(|a| (**a).as_str()).collect();
+        Ok(Storable::SeqOnHeap(Sequence::output(&*bin_name, &args)?))
+    }
+
+    /// the ints from start up to end, end excluded. `start..end` is desugared into it
+    pub unsafe fn range(start: i64, end: i64) -> Result<Storable> {
+        Ok(Storable::SeqOnHeap(Sequence::Range {
+            next: start,
+            end,
+            step: 1,
+            inclusive: false,
+        }))
+    }
+
+    /// the first n elements of the sequence. It consumes the sequence
+    pub unsafe fn take(mem: &mut Memory, n: i64, seq: SeqData) -> Result<Storable> {
+        let n = count(n)?;
+        let seq = mem.take_sequence(seq.0)?;
+        Ok(Storable::SeqOnHeap(Sequence::Take(Box::new(seq), n)))
+    }
+
+    /// the sequence without its first n elements. It consumes the sequence
+    pub unsafe fn skip(mem: &mut Memory, n: i64, seq: SeqData) -> Result<Storable> {
+        let n = count(n)?;
+        let seq = mem.take_sequence(seq.0)?;
+        Ok(Storable::SeqOnHeap(Sequence::Skip(Box::new(seq), n)))
+    }
+
+    /// consumes the rest of the sequence
+    pub unsafe fn to_list(mem: &mut Memory, seq: SeqData) -> Result<Storable> {
+        let mut elems = vec![];
+        while let Some(elem) = mem.next_elem(seq.0)? {
+            elems.push(elem);
+        }
+        Ok(Storable::VecOnHeap(elems))
+    }
+
+    fn count(n: i64) -> Result<usize> {
+        usize::try_from(n).map_err(|_| rt_err!("Expected a count, found a negative number: {}", n))
+    }
+
+    /// `start..=end` is desugared into it
+    pub unsafe fn range_inclusive(start: i64, end: i64) -> Result<Storable> {
+        Ok(Storable::SeqOnHeap(Sequence::Range {
+            next: start,
+            end,
+            step: 1,
+            inclusive: true,
+        }))
+    }
+
+    /// changes the step of a range, it consumes the range. `range step n` is desugared into it
+    pub unsafe fn range_step(mem: &mut Memory, range: SeqData, step: i64) -> Result<Storable> {
+        rt_assert!(step != 0, "The step of a range can't be 0");
+        match mem.take_sequence(range.0)? {
+            Sequence::Range {
+                next,
+                end,
+                inclusive,
+                ..
+            } => Ok(Storable::SeqOnHeap(Sequence::Range {
+                next,
+                end,
+                step,
+                inclusive,
+            })),
+            _ => Err(rt_err!("Only ranges can have a step")),
+        }
+    }
+
+    /// whether the list or sequence contains the value, `x in xs` is desugared into it. A
+    /// sequence is consumed up to the value, ranges are checked without consuming them
+    pub unsafe fn is_in(mem: &mut Memory, x: AnyData, xs: AnyData) -> Result<bool> {
+        let key = iteration::sort_key(&x.0);
+        let Some(SeqData(idx)) = xs.0.rtry_into() else {
+            let elems = iteration::expect_list(&xs.0)?;
+            return Ok(elems.iter().any(|e| iteration::sort_key(e) == key));
+        };
+        if let iteration::SortKey::Int(i) = key {
+            if let Some(contained) = mem.sequence(idx)?.range_contains(i) {
+                return Ok(contained);
+            }
+        }
+        while let Some(elem) = mem.next_elem(idx)? {
+            let elem = mem.alloc(elem);
+            let found = iteration::sort_key(&elem) == key;
+            mem.free(elem);
+            if found {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+
+    /// `xs[i]` is desugared into it. An int index gives the element, a sequence of indices, like
+    /// a range, a list of the elements. The result type is found out during type inference
+    pub unsafe fn index(mem: &mut Memory, list: AnyData, idx: AnyData) -> Result<Storable> {
+        let elems = iteration::expect_list(&list.0)?;
+        let elem_at = |i: i64| {
+            usize::try_from(i)
+                .ok()
+                .and_then(|i| elems.get(i))
+                .ok_or_else(|| {
+                    rt_err!(
+                        "Index {} out of range for a list of length {}",
+                        i,
+                        elems.len()
+                    )
+                })
+        };
+        if let Data::CopyVal(CopyValue::Int(i)) = idx.0 {
+            return iteration::copy(elem_at(i)?);
+        }
+        let SeqData(seq) = idx
+            .0
+            .rtry_into()
+            .ok_or_else(|| rt_err!("Expected an int or a range as index, found: {}", idx.0))?;
+        let mut selected = vec![];
+        while let Some(i) = mem.next_elem(seq)? {
+            let Storable::OnStack(Data::CopyVal(CopyValue::Int(i))) = i else {
+                return Err(rt_err!("Expected a sequence of ints as index"));
+            };
+            selected.push(iteration::copy(elem_at(i)?)?);
+        }
+        Ok(Storable::VecOnHeap(selected))
+    }
+
+    /// the wrapped value of the optional, or the default if there is none
+    pub unsafe fn or_else(opt: AnyData, default: AnyData) -> Result<Storable> {
+        match opt_value(&opt.0)? {
+            Some(d) => iteration::copy(d),
+            None => iteration::copy(&default.0),
+        }
+    }
+
+    pub unsafe fn is_some(opt: AnyData) -> Result<bool> {
+        Ok(opt_value(&opt.0)?.is_some())
+    }
+
+    unsafe fn opt_value(d: &RuntimeData) -> Result<Option<&RuntimeData>> {
+        match d {
+            Data::Ref(RuntimeRef::HeapRef(p)) => match &**p {
+                Value::Opt(inner) => Ok(inner.as_ref()),
+                _ => Err(rt_err!("Expected an optional value, found: {}", d)),
+            },
+            _ => Err(rt_err!("Expected an optional value, found: {}", d)),
+        }
     }
 
-    pub unsafe fn strcat(strs: Vec<*const String>) -> Result<String> {
-        let strs: Vec<&str> = strs.iter().map(|v| (&**v).as_ref()).collect();
-        Ok(strs.join(""))
+    pub unsafe fn first(pair: AnyData) -> Result<Storable> {
+        pair_elem(&pair.0, 0)
     }
+
+    pub unsafe fn second(pair: AnyData) -> Result<Storable> {
+        pair_elem(&pair.0, 1)
+    }
+
+    unsafe fn pair_elem(pair: &RuntimeData, idx: usize) -> Result<Storable> {
+        let elems = iteration::expect_list(pair)?;
+        let elem = elems
+            .get(idx)
+            .ok_or_else(|| rt_err!("Expected a pair, found: {}", pair))?;
+        iteration::copy(elem)
+    }
+}
+
+pub const BUILT_INS: [&'static str; 147] = [
+    "exec",
+    "strcat",
+    "spawn",
+    "isRunning",
+    "wait",
+    "captured",
+    "kill",
+    "glob",
+    "globStrict",
+    "escapeGlob",
+    "globMatch",
+    "read",
+    "write",
+    "exists",
+    "isDir",
+    "isFile",
+    "isRelative",
+    "name",
+    "stem",
+    "extension",
+    "parent",
+    "joinPath",
+    "canonicalize",
+    "expandHome",
+    "readFile",
+    "readLines",
+    "writeFile",
+    "appendFile",
+    "delete",
+    "copy",
+    "move",
+    "makeDir",
+    "split",
+    "lines",
+    "join",
+    "trim",
+    "trimStart",
+    "trimEnd",
+    "replace",
+    "startsWith",
+    "endsWith",
+    "contains",
+    "upper",
+    "lower",
+    "len",
+    "byteLen",
+    "slice",
+    "byteSlice",
+    "regex",
+    "matches",
+    "find",
+    "findAll",
+    "captures",
+    "replaceAll",
+    "fromJson",
+    "toJson",
+    "toJsonPretty",
+    "jsonType",
+    "jsonGet",
+    "jsonAt",
+    "jsonKeys",
+    "jsonList",
+    "jsonStr",
+    "jsonInt",
+    "jsonFloat",
+    "jsonBool",
+    "getEnv",
+    "getEnvOr",
+    "hasEnv",
+    "setEnv",
+    "unsetEnv",
+    "withEnv",
+    "withCwd",
+    "withTmpPath",
+    "withTmpDir",
+    "print",
+    "println",
+    "eprint",
+    "eprintln",
+    "readLine",
+    "readAll",
+    "stdinLines",
+    "prompt",
+    "confirm",
+    "password",
+    "color",
+    "bgColor",
+    "bold",
+    "dim",
+    "italic",
+    "underline",
+    "isTerminal",
+    "termWidth",
+    "progress",
+    "withSpinner",
+    "now",
+    "since",
+    "addTime",
+    "timeBetween",
+    "formatTime",
+    "formatUtc",
+    "parseTime",
+    "parseUtc",
+    "duration",
+    "formatDuration",
+    "sleep",
+    "measure",
+    "parseInt",
+    "parseIntOr",
+    "parseIntRadix",
+    "parseFloat",
+    "parseFloatOr",
+    "toFloat",
+    "fixed",
+    "padLeft",
+    "padRight",
+    "formatBytes",
+    "map",
+    "filter",
+    "fold",
+    "each",
+    "for",
+    "any",
+    "all",
+    "sortBy",
+    "mapIndexed",
+    "sort",
+    "unique",
+    "zip",
+    "enumerate",
+    "flatten",
+    "fileLines",
+    "outputLines",
+    "range",
+    "take",
+    "skip",
+    "toList",
+    "rangeInclusive",
+    "rangeStep",
+    "isIn",
+    "index",
+    "execRedirected",
+    "spawnRedirected",
+    "orElse",
+    "isSome",
+    "first",
+    "second",
+];
+
+/// The type variables of the generic built ins. T is the type of the elements of the list
+fn var_t() -> DataType {
+    DataType::var(0)
+}
+
+fn var_u() -> DataType {
+    DataType::var(1)
+}
+
+/// lists and lazy sequences, the higher order built ins take both
+fn iterable(elem: DataType) -> TypeSet {
+    TypeSet::SomeTypes([elem.clone().vec(), elem.seq()].into())
+}
+
+/// the type of the target of a redirection operator, unit if the stream isn't redirected
+fn redirect_target() -> TypeSet {
+    TypeSet::SomeTypes([DataType::stream(), DataType::str(), DataType::unit()].into())
 }
 
-pub const BUILT_INS: [&'static str; 2] = ["exec", "strcat"];
+/// the type of the function that is passed to a higher order built in
+fn fragment(args: Vec<DataType>, result: DataType) -> TypeSet {
+    let args = args.into_iter().map(TypeSet::from).collect();
+    DataType::fragment(Signature::new().args(args).result(result.into())).into()
+}
 
 pub fn signatures(f_name: &str) -> Option<Signature> {
     Some(match f_name {
@@ -48,6 +1329,446 @@ pub fn signatures(f_name: &str) -> Option<Signature> {
         "strcat" => Signature::new()
             .variadic(DataType::str().into())
             .result(DataType::str().into()),
+        "spawn" => Signature::new()
+            .arg(DataType::str().into())
+            .variadic(DataType::str().into())
+            .result(DataType::proc_handle().into()),
+        "isRunning" => Signature::new()
+            .arg(DataType::proc_handle().into())
+            .result(DataType::bool().into()),
+        "wait" => Signature::new()
+            .arg(DataType::proc_handle().into())
+            .result(DataType::int().into()),
+        "captured" => Signature::new()
+            .arg(DataType::proc_handle().into())
+            .result(DataType::pair(DataType::str(), DataType::str()).into()),
+        "kill" => {
+            Signature::new().args(vec![DataType::proc_handle().into(), DataType::int().into()])
+        }
+        "glob" | "globStrict" => Signature::new()
+            .arg(DataType::str().into())
+            .result(DataType::str().vec().into()),
+        "escapeGlob" => Signature::new()
+            .arg(DataType::str().into())
+            .result(DataType::str().into()),
+        "globMatch" => Signature::new()
+            .args(vec![DataType::str().into(), DataType::str().into()])
+            .result(DataType::bool().into()),
+        "read" => Signature::new()
+            .arg(DataType::stream().into())
+            .result(DataType::str().into()),
+        "write" => Signature::new().args(vec![DataType::stream().into(), DataType::str().into()]),
+        "exists" => Signature::new()
+            .arg(DataType::str().into())
+            .result(DataType::bool().into()),
+        "isDir" => Signature::new()
+            .arg(DataType::str().into())
+            .result(DataType::bool().into()),
+        "isFile" => Signature::new()
+            .arg(DataType::str().into())
+            .result(DataType::bool().into()),
+        "isRelative" => Signature::new()
+            .arg(DataType::str().into())
+            .result(DataType::bool().into()),
+        "name" => Signature::new()
+            .arg(DataType::str().into())
+            .result(DataType::str().into()),
+        "stem" => Signature::new()
+            .arg(DataType::str().into())
+            .result(DataType::str().into()),
+        "extension" => Signature::new()
+            .arg(DataType::str().into())
+            .result(DataType::str().into()),
+        "parent" => Signature::new()
+            .arg(DataType::str().into())
+            .result(DataType::str().into()),
+        "joinPath" => Signature::new()
+            .args(vec![DataType::str().into(), DataType::str().into()])
+            .result(DataType::str().into()),
+        "canonicalize" => Signature::new()
+            .arg(DataType::str().into())
+            .result(DataType::str().into()),
+        "expandHome" => Signature::new()
+            .arg(DataType::str().into())
+            .result(DataType::str().into()),
+        "readFile" => Signature::new()
+            .arg(DataType::str().into())
+            .result(DataType::str().into()),
+        "readLines" => Signature::new()
+            .arg(DataType::str().into())
+            .result(DataType::str().vec().into()),
+        "writeFile" => Signature::new().args(vec![DataType::str().into(), DataType::str().into()]),
+        "appendFile" => Signature::new().args(vec![DataType::str().into(), DataType::str().into()]),
+        "delete" => Signature::new().arg(DataType::str().into()),
+        "copy" => Signature::new().args(vec![DataType::str().into(), DataType::str().into()]),
+        "move" => Signature::new().args(vec![DataType::str().into(), DataType::str().into()]),
+        "makeDir" => Signature::new().arg(DataType::str().into()),
+        "split" => Signature::new()
+            .args(vec![DataType::str().into(), DataType::str().into()])
+            .result(DataType::str().vec().into()),
+        "lines" => Signature::new()
+            .arg(DataType::str().into())
+            .result(DataType::str().vec().into()),
+        "join" => Signature::new()
+            .args(vec![DataType::str().vec().into(), DataType::str().into()])
+            .result(DataType::str().into()),
+        "trim" => Signature::new()
+            .arg(DataType::str().into())
+            .result(DataType::str().into()),
+        "trimStart" => Signature::new()
+            .arg(DataType::str().into())
+            .result(DataType::str().into()),
+        "trimEnd" => Signature::new()
+            .arg(DataType::str().into())
+            .result(DataType::str().into()),
+        "replace" => Signature::new()
+            .args(vec![
+                DataType::str().into(),
+                DataType::str().into(),
+                DataType::str().into(),
+            ])
+            .result(DataType::str().into()),
+        "startsWith" => Signature::new()
+            .args(vec![DataType::str().into(), DataType::str().into()])
+            .result(DataType::bool().into()),
+        "endsWith" => Signature::new()
+            .args(vec![DataType::str().into(), DataType::str().into()])
+            .result(DataType::bool().into()),
+        "contains" => Signature::new()
+            .args(vec![DataType::str().into(), DataType::str().into()])
+            .result(DataType::bool().into()),
+        "upper" => Signature::new()
+            .arg(DataType::str().into())
+            .result(DataType::str().into()),
+        "lower" => Signature::new()
+            .arg(DataType::str().into())
+            .result(DataType::str().into()),
+        "len" => Signature::new()
+            .arg(DataType::str().into())
+            .result(DataType::int().into()),
+        "byteLen" => Signature::new()
+            .arg(DataType::str().into())
+            .result(DataType::int().into()),
+        "slice" => Signature::new()
+            .args(vec![
+                DataType::str().into(),
+                DataType::int().into(),
+                DataType::int().into(),
+            ])
+            .result(DataType::str().into()),
+        "byteSlice" => Signature::new()
+            .args(vec![
+                DataType::str().into(),
+                DataType::int().into(),
+                DataType::int().into(),
+            ])
+            .result(DataType::str().into()),
+        "regex" => Signature::new()
+            .arg(DataType::str().into())
+            .result(DataType::regex().into()),
+        "matches" => Signature::new()
+            .args(vec![DataType::regex().into(), DataType::str().into()])
+            .result(DataType::bool().into()),
+        "find" => Signature::new()
+            .args(vec![DataType::regex().into(), DataType::str().into()])
+            .result(DataType::str().vec().into()),
+        "findAll" => Signature::new()
+            .args(vec![DataType::regex().into(), DataType::str().into()])
+            .result(DataType::str().vec().vec().into()),
+        "captures" => Signature::new()
+            .args(vec![DataType::regex().into(), DataType::str().into()])
+            .result(DataType::dict(DataType::str(), DataType::str()).into()),
+        "replaceAll" => Signature::new()
+            .args(vec![
+                DataType::regex().into(),
+                DataType::str().into(),
+                DataType::str().into(),
+            ])
+            .result(DataType::str().into()),
+        "fromJson" => Signature::new()
+            .arg(DataType::str().into())
+            .result(DataType::json().into()),
+        "toJson" => Signature::new()
+            .arg(TypeSet::AllTypes)
+            .result(DataType::str().into()),
+        "toJsonPretty" => Signature::new()
+            .arg(TypeSet::AllTypes)
+            .result(DataType::str().into()),
+        "jsonType" => Signature::new()
+            .arg(DataType::json().into())
+            .result(DataType::str().into()),
+        "jsonGet" => Signature::new()
+            .args(vec![DataType::json().into(), DataType::str().into()])
+            .result(DataType::json().into()),
+        "jsonAt" => Signature::new()
+            .args(vec![DataType::json().into(), DataType::int().into()])
+            .result(DataType::json().into()),
+        "jsonKeys" => Signature::new()
+            .arg(DataType::json().into())
+            .result(DataType::str().vec().into()),
+        "jsonList" => Signature::new()
+            .arg(DataType::json().into())
+            .result(DataType::json().vec().opt().into()),
+        "jsonStr" => Signature::new()
+            .arg(DataType::json().into())
+            .result(DataType::str().opt().into()),
+        "jsonInt" => Signature::new()
+            .arg(DataType::json().into())
+            .result(DataType::int().opt().into()),
+        "jsonFloat" => Signature::new()
+            .arg(DataType::json().into())
+            .result(DataType::float().opt().into()),
+        "jsonBool" => Signature::new()
+            .arg(DataType::json().into())
+            .result(DataType::bool().opt().into()),
+        "getEnv" => Signature::new()
+            .arg(DataType::str().into())
+            .result(DataType::str().opt().into()),
+        "getEnvOr" => Signature::new()
+            .args(vec![DataType::str().into(), DataType::str().into()])
+            .result(DataType::str().into()),
+        "hasEnv" => Signature::new()
+            .arg(DataType::str().into())
+            .result(DataType::bool().into()),
+        "setEnv" => Signature::new().args(vec![DataType::str().into(), DataType::str().into()]),
+        "unsetEnv" => Signature::new().arg(DataType::str().into()),
+        "withEnv" => Signature::new().args(vec![DataType::str().into(), DataType::str().into()]),
+        "withCwd" => Signature::new().arg(DataType::str().into()),
+        "withTmpPath" => Signature::new().result(DataType::str().into()),
+        "withTmpDir" => Signature::new().result(DataType::str().into()),
+        "print" => Signature::new().variadic(TypeSet::AllTypes),
+        "println" => Signature::new().variadic(TypeSet::AllTypes),
+        "eprint" => Signature::new().variadic(TypeSet::AllTypes),
+        "eprintln" => Signature::new().variadic(TypeSet::AllTypes),
+        "readLine" => Signature::new().result(DataType::str().into()),
+        "readAll" => Signature::new().result(DataType::str().into()),
+        "stdinLines" => Signature::new().result(DataType::str().seq().into()),
+        "prompt" => Signature::new()
+            .arg(DataType::str().into())
+            .result(DataType::str().into()),
+        "confirm" => Signature::new()
+            .args(vec![DataType::str().into(), DataType::str().into()])
+            .result(DataType::bool().into()),
+        "password" => Signature::new()
+            .arg(DataType::str().into())
+            .result(DataType::str().into()),
+        "color" => Signature::new()
+            .args(vec![DataType::str().into(), DataType::str().into()])
+            .result(DataType::str().into()),
+        "bgColor" => Signature::new()
+            .args(vec![DataType::str().into(), DataType::str().into()])
+            .result(DataType::str().into()),
+        "bold" => Signature::new()
+            .arg(DataType::str().into())
+            .result(DataType::str().into()),
+        "dim" => Signature::new()
+            .arg(DataType::str().into())
+            .result(DataType::str().into()),
+        "italic" => Signature::new()
+            .arg(DataType::str().into())
+            .result(DataType::str().into()),
+        "underline" => Signature::new()
+            .arg(DataType::str().into())
+            .result(DataType::str().into()),
+        "isTerminal" => Signature::new()
+            .arg(DataType::stream().into())
+            .result(DataType::bool().into()),
+        "termWidth" => Signature::new().result(DataType::int().into()),
+        "progress" => Signature::new().args(vec![
+            DataType::int().into(),
+            DataType::int().into(),
+            DataType::str().into(),
+        ]),
+        "withSpinner" => Signature::new().arg(DataType::str().into()),
+        "now" => Signature::new().result(DataType::int().into()),
+        "since" => Signature::new()
+            .arg(DataType::int().into())
+            .result(DataType::int().into()),
+        "addTime" => Signature::new()
+            .args(vec![DataType::int().into(), DataType::int().into()])
+            .result(DataType::int().into()),
+        "timeBetween" => Signature::new()
+            .args(vec![DataType::int().into(), DataType::int().into()])
+            .result(DataType::int().into()),
+        "formatTime" => Signature::new()
+            .args(vec![DataType::int().into(), DataType::str().into()])
+            .result(DataType::str().into()),
+        "formatUtc" => Signature::new()
+            .args(vec![DataType::int().into(), DataType::str().into()])
+            .result(DataType::str().into()),
+        "parseTime" => Signature::new()
+            .args(vec![DataType::str().into(), DataType::str().into()])
+            .result(DataType::int().into()),
+        "parseUtc" => Signature::new()
+            .args(vec![DataType::str().into(), DataType::str().into()])
+            .result(DataType::int().into()),
+        "duration" => Signature::new()
+            .arg(DataType::str().into())
+            .result(DataType::int().into()),
+        "formatDuration" => Signature::new()
+            .arg(DataType::int().into())
+            .result(DataType::str().into()),
+        "sleep" => Signature::new().arg(DataType::int().into()),
+        "measure" => Signature::new().result(DataType::int().into()),
+        "parseInt" => Signature::new()
+            .arg(DataType::str().into())
+            .result(DataType::int().opt().into()),
+        "parseIntOr" => Signature::new()
+            .args(vec![DataType::str().into(), DataType::int().into()])
+            .result(DataType::int().into()),
+        "parseIntRadix" => Signature::new()
+            .args(vec![DataType::str().into(), DataType::int().into()])
+            .result(DataType::int().opt().into()),
+        "parseFloat" => Signature::new()
+            .arg(DataType::str().into())
+            .result(DataType::float().opt().into()),
+        "parseFloatOr" => Signature::new()
+            .args(vec![DataType::str().into(), DataType::float().into()])
+            .result(DataType::float().into()),
+        "toFloat" => Signature::new()
+            .arg(DataType::int().into())
+            .result(DataType::float().into()),
+        "fixed" => Signature::new()
+            .args(vec![DataType::float().into(), DataType::int().into()])
+            .result(DataType::str().into()),
+        "padLeft" => Signature::new()
+            .args(vec![
+                DataType::str().into(),
+                DataType::int().into(),
+                DataType::str().into(),
+            ])
+            .result(DataType::str().into()),
+        "padRight" => Signature::new()
+            .args(vec![
+                DataType::str().into(),
+                DataType::int().into(),
+                DataType::str().into(),
+            ])
+            .result(DataType::str().into()),
+        "formatBytes" => Signature::new()
+            .arg(DataType::int().into())
+            .result(DataType::str().into()),
+        "map" => Signature::new()
+            .args(vec![fragment(vec![var_t()], var_u()), iterable(var_t())])
+            .result(var_u().vec().into()),
+        "filter" => Signature::new()
+            .args(vec![
+                fragment(vec![var_t()], DataType::bool()),
+                iterable(var_t()),
+            ])
+            .result(var_t().vec().into()),
+        "fold" => Signature::new()
+            .args(vec![
+                fragment(vec![var_u(), var_t()], var_u()),
+                var_u().into(),
+                iterable(var_t()),
+            ])
+            .result(var_u().into()),
+        "each" => Signature::new()
+            .args(vec![fragment(vec![var_t()], var_u()), iterable(var_t())])
+            .result(DataType::unit().into()),
+        "for" => Signature::new()
+            .args(vec![iterable(var_t()), fragment(vec![var_t()], var_u())])
+            .result(DataType::unit().into()),
+        "any" => Signature::new()
+            .args(vec![
+                fragment(vec![var_t()], DataType::bool()),
+                iterable(var_t()),
+            ])
+            .result(DataType::bool().into()),
+        "all" => Signature::new()
+            .args(vec![
+                fragment(vec![var_t()], DataType::bool()),
+                iterable(var_t()),
+            ])
+            .result(DataType::bool().into()),
+        "sortBy" => Signature::new()
+            .args(vec![fragment(vec![var_t()], var_u()), var_t().vec().into()])
+            .result(var_t().vec().into()),
+        "mapIndexed" => Signature::new()
+            .args(vec![
+                fragment(vec![DataType::int(), var_t()], var_u()),
+                iterable(var_t()),
+            ])
+            .result(var_u().vec().into()),
+        "sort" => Signature::new()
+            .arg(var_t().vec().into())
+            .result(var_t().vec().into()),
+        "unique" => Signature::new()
+            .arg(var_t().vec().into())
+            .result(var_t().vec().into()),
+        "zip" => Signature::new()
+            .args(vec![var_t().vec().into(), var_u().vec().into()])
+            .result(DataType::pair(var_t(), var_u()).vec().into()),
+        "enumerate" => Signature::new()
+            .arg(var_t().vec().into())
+            .result(DataType::pair(DataType::int(), var_t()).vec().into()),
+        "flatten" => Signature::new()
+            .arg(var_t().vec().vec().into())
+            .result(var_t().vec().into()),
+        "fileLines" => Signature::new()
+            .arg(DataType::str().into())
+            .result(DataType::str().seq().into()),
+        "outputLines" => Signature::new()
+            .arg(DataType::str().into())
+            .variadic(DataType::str().into())
+            .result(DataType::str().seq().into()),
+        "range" => Signature::new()
+            .args(vec![DataType::int().into(), DataType::int().into()])
+            .result(DataType::int().seq().into()),
+        "take" => Signature::new()
+            .args(vec![DataType::int().into(), var_t().seq().into()])
+            .result(var_t().seq().into()),
+        "skip" => Signature::new()
+            .args(vec![DataType::int().into(), var_t().seq().into()])
+            .result(var_t().seq().into()),
+        "toList" => Signature::new()
+            .arg(var_t().seq().into())
+            .result(var_t().vec().into()),
+        "rangeInclusive" => Signature::new()
+            .args(vec![DataType::int().into(), DataType::int().into()])
+            .result(DataType::int().seq().into()),
+        "rangeStep" => Signature::new()
+            .args(vec![DataType::int().seq().into(), DataType::int().into()])
+            .result(DataType::int().seq().into()),
+        "isIn" => Signature::new()
+            .args(vec![var_t().into(), iterable(var_t())])
+            .result(DataType::bool().into()),
+        "index" => Signature::new()
+            .args(vec![
+                var_t().vec().into(),
+                TypeSet::SomeTypes([DataType::int(), DataType::int().seq()].into()),
+            ])
+            .result(var_t().into()),
+        "execRedirected" => Signature::new()
+            .args(vec![
+                redirect_target(),
+                redirect_target(),
+                redirect_target(),
+                DataType::str().into(),
+            ])
+            .variadic(DataType::str().into()),
+        "spawnRedirected" => Signature::new()
+            .args(vec![
+                redirect_target(),
+                redirect_target(),
+                redirect_target(),
+                DataType::str().into(),
+            ])
+            .variadic(DataType::str().into())
+            .result(DataType::proc_handle().into()),
+        "orElse" => Signature::new()
+            .args(vec![var_t().opt().into(), var_t().into()])
+            .result(var_t().into()),
+        "isSome" => Signature::new()
+            .arg(var_t().opt().into())
+            .result(DataType::bool().into()),
+        "first" => Signature::new()
+            .arg(DataType::pair(var_t(), var_u()).into())
+            .result(var_t().into()),
+        "second" => Signature::new()
+            .arg(DataType::pair(var_t(), var_u()).into())
+            .result(var_u().into()),
         _ => {
             return None;
         }
@@ -58,10 +1779,583 @@ pub fn opcode(f_name: &str) -> Option<OpCode> {
     Some(match f_name {
         "exec" => OpCode::Exec,
         "strcat" => OpCode::StrCat,
+        "spawn" => OpCode::Spawn,
+        "isRunning" => OpCode::IsRunning,
+        "wait" => OpCode::Wait,
+        "captured" => OpCode::Captured,
+        "kill" => OpCode::Kill,
+        "glob" => OpCode::Glob,
+        "globStrict" => OpCode::GlobStrict,
+        "escapeGlob" => OpCode::EscapeGlob,
+        "globMatch" => OpCode::GlobMatch,
+        "read" => OpCode::Read,
+        "write" => OpCode::Write,
+        "exists" => OpCode::Exists,
+        "isDir" => OpCode::IsDir,
+        "isFile" => OpCode::IsFile,
+        "isRelative" => OpCode::IsRelative,
+        "name" => OpCode::Name,
+        "stem" => OpCode::Stem,
+        "extension" => OpCode::Extension,
+        "parent" => OpCode::Parent,
+        "joinPath" => OpCode::JoinPath,
+        "canonicalize" => OpCode::Canonicalize,
+        "expandHome" => OpCode::ExpandHome,
+        "readFile" => OpCode::ReadFile,
+        "readLines" => OpCode::ReadLines,
+        "writeFile" => OpCode::WriteFile,
+        "appendFile" => OpCode::AppendFile,
+        "delete" => OpCode::Delete,
+        "copy" => OpCode::Copy,
+        "move" => OpCode::Move,
+        "makeDir" => OpCode::MakeDir,
+        "split" => OpCode::Split,
+        "lines" => OpCode::Lines,
+        "join" => OpCode::Join,
+        "trim" => OpCode::Trim,
+        "trimStart" => OpCode::TrimStart,
+        "trimEnd" => OpCode::TrimEnd,
+        "replace" => OpCode::Replace,
+        "startsWith" => OpCode::StartsWith,
+        "endsWith" => OpCode::EndsWith,
+        "contains" => OpCode::Contains,
+        "upper" => OpCode::Upper,
+        "lower" => OpCode::Lower,
+        "len" => OpCode::Len,
+        "byteLen" => OpCode::ByteLen,
+        "slice" => OpCode::Slice,
+        "byteSlice" => OpCode::ByteSlice,
+        "regex" => OpCode::Regex,
+        "matches" => OpCode::Matches,
+        "find" => OpCode::Find,
+        "findAll" => OpCode::FindAll,
+        "captures" => OpCode::Captures,
+        "replaceAll" => OpCode::ReplaceAll,
+        "fromJson" => OpCode::FromJson,
+        "toJson" => OpCode::ToJson,
+        "toJsonPretty" => OpCode::ToJsonPretty,
+        "jsonType" => OpCode::JsonType,
+        "jsonGet" => OpCode::JsonGet,
+        "jsonAt" => OpCode::JsonAt,
+        "jsonKeys" => OpCode::JsonKeys,
+        "jsonList" => OpCode::JsonList,
+        "jsonStr" => OpCode::JsonStr,
+        "jsonInt" => OpCode::JsonInt,
+        "jsonFloat" => OpCode::JsonFloat,
+        "jsonBool" => OpCode::JsonBool,
+        "getEnv" => OpCode::GetEnv,
+        "getEnvOr" => OpCode::GetEnvOr,
+        "hasEnv" => OpCode::HasEnv,
+        "setEnv" => OpCode::SetEnv,
+        "unsetEnv" => OpCode::UnsetEnv,
+        "withEnv" => OpCode::WithEnv,
+        "withCwd" => OpCode::WithCwd,
+        "withTmpPath" => OpCode::WithTmpPath,
+        "withTmpDir" => OpCode::WithTmpDir,
+        "print" => OpCode::Print,
+        "println" => OpCode::Println,
+        "eprint" => OpCode::Eprint,
+        "eprintln" => OpCode::Eprintln,
+        "readLine" => OpCode::ReadLine,
+        "readAll" => OpCode::ReadAll,
+        "stdinLines" => OpCode::StdinLines,
+        "prompt" => OpCode::Prompt,
+        "confirm" => OpCode::Confirm,
+        "password" => OpCode::Password,
+        "color" => OpCode::Color,
+        "bgColor" => OpCode::BgColor,
+        "bold" => OpCode::Bold,
+        "dim" => OpCode::Dim,
+        "italic" => OpCode::Italic,
+        "underline" => OpCode::Underline,
+        "isTerminal" => OpCode::IsTerminal,
+        "termWidth" => OpCode::TermWidth,
+        "progress" => OpCode::Progress,
+        "withSpinner" => OpCode::WithSpinner,
+        "now" => OpCode::Now,
+        "since" => OpCode::Since,
+        "addTime" => OpCode::AddTime,
+        "timeBetween" => OpCode::TimeBetween,
+        "formatTime" => OpCode::FormatTime,
+        "formatUtc" => OpCode::FormatUtc,
+        "parseTime" => OpCode::ParseTime,
+        "parseUtc" => OpCode::ParseUtc,
+        "duration" => OpCode::Duration,
+        "formatDuration" => OpCode::FormatDuration,
+        "sleep" => OpCode::Sleep,
+        "measure" => OpCode::Measure,
+        "parseInt" => OpCode::ParseInt,
+        "parseIntOr" => OpCode::ParseIntOr,
+        "parseIntRadix" => OpCode::ParseIntRadix,
+        "parseFloat" => OpCode::ParseFloat,
+        "parseFloatOr" => OpCode::ParseFloatOr,
+        "toFloat" => OpCode::ToFloat,
+        "fixed" => OpCode::Fixed,
+        "padLeft" => OpCode::PadLeft,
+        "padRight" => OpCode::PadRight,
+        "formatBytes" => OpCode::FormatBytes,
+        "map" => OpCode::Map,
+        "filter" => OpCode::Filter,
+        "fold" => OpCode::Fold,
+        "each" => OpCode::Each,
+        "for" => OpCode::For,
+        "any" => OpCode::Any,
+        "all" => OpCode::All,
+        "sortBy" => OpCode::SortBy,
+        "mapIndexed" => OpCode::MapIndexed,
+        "sort" => OpCode::Sort,
+        "unique" => OpCode::Unique,
+        "zip" => OpCode::Zip,
+        "enumerate" => OpCode::Enumerate,
+        "flatten" => OpCode::Flatten,
+        "fileLines" => OpCode::FileLines,
+        "outputLines" => OpCode::OutputLines,
+        "range" => OpCode::Range,
+        "take" => OpCode::Take,
+        "skip" => OpCode::Skip,
+        "toList" => OpCode::ToList,
+        "rangeInclusive" => OpCode::RangeInclusive,
+        "rangeStep" => OpCode::RangeStep,
+        "isIn" => OpCode::IsIn,
+        "index" => OpCode::Index,
+        "execRedirected" => OpCode::ExecRedirected,
+        "spawnRedirected" => OpCode::SpawnRedirected,
+        "orElse" => OpCode::OrElse,
+        "isSome" => OpCode::IsSome,
+        "first" => OpCode::First,
+        "second" => OpCode::Second,
         _ => return None,
     })
 }
 
+/// Scoped built ins take a body as last argument, which isn't passed to them. It is evaluated
+/// after the built in changed the context, e.g. set an environment variable, and before the
+/// change is undone by [OpCode::LeaveScope]. The call evaluates to the body. If the body is a
+/// fragment, its argument is bound to the result of the built in instead
+pub fn is_scoped(f_name: &str) -> bool {
+    matches!(
+        f_name,
+        "withEnv" | "withCwd" | "withTmpPath" | "withTmpDir" | "withSpinner" | "measure"
+    )
+}
+
+/// Over a sequence, these higher order built ins make a lazy sequence, which runs their fragment
+/// whenever an element is pulled from it, see [OpCode::Lazy]. Over a list, they make a list
+pub fn lazy_kind(f_name: &str) -> Option<IterKind> {
+    match f_name {
+        "map" => Some(IterKind::Map),
+        "filter" => Some(IterKind::Filter),
+        "mapIndexed" => Some(IterKind::MapIndexed),
+        _ => None,
+    }
+}
+
+unsafe fn command(bin_name: &str, args: &[*const String]) -> process::Command {
+    let mut cmd = process::Command::new(bin_name);
+    cmd.args(args.iter().map(|a| &**a));
+    cmd
+}
+
+/// runs the program until it ends, it must succeed. A spinner pauses meanwhile, since the program
+/// may write to the terminal too
+fn run(mut cmd: process::Command, bin_name: &str) -> Result<()> {
+    clear_spinner().paused = true;
+    let stat = cmd.status();
+    spinner_line().paused = false;
+    let stat = stat.map_err(|e| rt_err!("Executing {}: {}", bin_name, e))?;
+    rt_assert!(stat.success(), "{} did not execute successfully", bin_name);
+    Ok(())
+}
+
+/// starts the program in the background, the memory owns it from then on
+fn start_bg(mem: &mut Memory, mut cmd: process::Command, bin_name: &str) -> Result<ProcHandle> {
+    let child = cmd
+        .spawn()
+        .map_err(|e| rt_err!("Spawning {}: {}", bin_name, e))?;
+    let handle = ProcHandle {
+        idx: mem.children.len(),
+        pid: child.id(),
+    };
+    mem.children.push(BgProcess::new(child));
+    Ok(handle)
+}
+
+/// Where a redirection operator sends a stream of a program
+enum Target {
+    Stream(Stream),
+    File(String),
+}
+
+/// Applies the redirection operators `<@`, `@>`, `!>` and `!@>`. The target of each stream is a
+/// stream of the script, like NULL or ERR, or the path of a file. The targets of the streams that
+/// aren't redirected are unit, those streams are left as they are.
+/// When stdout and stderr go to the same file, the file is opened once, so the outputs don't
+/// overwrite each other. Only the output of programs in the background can be captured, since
+/// nothing would read the output of the others while they run
+unsafe fn redirect(
+    cmd: &mut process::Command,
+    stdin: &RuntimeData,
+    stdout: &RuntimeData,
+    stderr: &RuntimeData,
+    in_background: bool,
+) -> Result<()> {
+    match target_of(stdin) {
+        Some(Target::Stream(Stream::In)) | None => {}
+        Some(Target::Stream(Stream::Null)) => {
+            cmd.stdin(process::Stdio::null());
+        }
+        Some(Target::Stream(s)) => return Err(rt_err!("Can't read from {}", s)),
+        Some(Target::File(path)) => {
+            let file = fs::File::open(&path).map_err(|e| rt_err!("Opening {}: {}", path, e))?;
+            cmd.stdin(file);
+        }
+    }
+    let (stdout, stderr) = (target_of(stdout), target_of(stderr));
+    if let (Some(Target::File(out)), Some(Target::File(err))) = (&stdout, &stderr) {
+        if out == err {
+            let file = create_for_redirect(out)?;
+            let clone = file
+                .try_clone()
+                .map_err(|e| rt_err!("Opening {}: {}", out, e))?;
+            cmd.stdout(file).stderr(clone);
+            return Ok(());
+        }
+    }
+    if let Some(target) = stdout {
+        cmd.stdout(output_stdio(target, in_background)?);
+    }
+    if let Some(target) = stderr {
+        cmd.stderr(output_stdio(target, in_background)?);
+    }
+    Ok(())
+}
+
+unsafe fn target_of(d: &RuntimeData) -> Option<Target> {
+    if let Some(stream) = d.rtry_into() {
+        return Some(Target::Stream(stream));
+    }
+    let path: *const String = d.rtry_into()?;
+    Some(Target::File((*path).clone()))
+}
+
+fn output_stdio(target: Target, in_background: bool) -> Result<process::Stdio> {
+    let fd = match target {
+        Target::Stream(Stream::Null) => return Ok(process::Stdio::null()),
+        Target::Stream(Stream::Capture) if in_background => return Ok(process::Stdio::piped()),
+        Target::Stream(Stream::Capture) => {
+            return Err(rt_err!(
+                "Only the output of programs in the background can be captured"
+            ))
+        }
+        Target::Stream(Stream::In) => return Err(rt_err!("Can't write to {}", Stream::In)),
+        Target::Stream(Stream::Out) => std::io::stdout().as_fd().try_clone_to_owned(),
+        Target::Stream(Stream::Err) => std::io::stderr().as_fd().try_clone_to_owned(),
+        Target::File(path) => return Ok(create_for_redirect(&path)?.into()),
+    };
+    fd.map(process::Stdio::from)
+        .map_err(|e| rt_err!("Redirecting to a stream: {}", e))
+}
+
+fn create_for_redirect(path: &str) -> Result<fs::File> {
+    fs::File::create(path).map_err(|e| rt_err!("Creating {}: {}", path, e))
+}
+
+/// the stream is flushed, so the output isn't mixed up with the output of child processes
+fn write_to(stream: Stream, s: &str) -> Result<()> {
+    let s = if stream_is_terminal(stream) {
+        Cow::Borrowed(s)
+    } else {
+        unstyled(s)
+    };
+    // holding the lock, so a spinner doesn't draw in the middle of the output
+    let _line = clear_spinner();
+    let res = match stream {
+        Stream::Out => {
+            let mut out = std::io::stdout();
+            out.write_all(s.as_bytes()).and_then(|_| out.flush())
+        }
+        Stream::Err => std::io::stderr().write_all(s.as_bytes()),
+        Stream::Null => Ok(()),
+        Stream::In | Stream::Capture => return Err(rt_err!("Can't write to {}", stream)),
+    };
+    res.map_err(|e| rt_err!("Writing to {}: {}", stream, e))
+}
+
+/// the fill that is needed to reach the width, fill must be a single char
+fn padding(s: &str, width: i64, fill: &str) -> Result<String> {
+    let mut fill_chars = fill.chars();
+    let (Some(c), None) = (fill_chars.next(), fill_chars.next()) else {
+        return Err(rt_err!("The fill must be a single char, found: {}", fill));
+    };
+    let missing = (width.max(0) as usize).saturating_sub(s.chars().count());
+    Ok(c.to_string().repeat(missing))
+}
+
+/// e.g. 1d 2h 0m 5s, or 1.5s for durations below a minute
+pub fn format_duration(ms: i64) -> String {
+    let sign = if ms < 0 { "-" } else { "" };
+    let ms = ms.unsigned_abs();
+    if ms < 1000 {
+        return format!("{}{}ms", sign, ms);
+    }
+    if ms < 60_000 {
+        let s = format!("{}.{:03}", ms / 1000, ms % 1000);
+        return format!("{}{}s", sign, s.trim_end_matches('0').trim_end_matches('.'));
+    }
+    let units = [
+        (86_400_000, "d"),
+        (3_600_000, "h"),
+        (60_000, "m"),
+        (1000, "s"),
+    ];
+    let mut parts = vec![];
+    let mut rest = ms;
+    for (size, unit) in units {
+        if !parts.is_empty() || rest >= size {
+            parts.push(format!("{}{}", rest / size, unit));
+        }
+        rest %= size;
+    }
+    format!("{}{}", sign, parts.join(" "))
+}
+
+fn parse_duration(s: &str) -> Option<i64> {
+    let mut rest = s.trim();
+    let mut total = 0.0;
+    if rest.is_empty() {
+        return None;
+    }
+    while !rest.is_empty() {
+        let num_len = rest.find(|c: char| !(c.is_ascii_digit() || c == '.'))?;
+        let (num, tail) = rest.split_at(num_len);
+        let unit_len = tail
+            .find(|c: char| !c.is_ascii_alphabetic())
+            .unwrap_or(tail.len());
+        let (unit, tail) = tail.split_at(unit_len);
+        let factor = match unit {
+            "ms" => 1.0,
+            "s" => 1000.0,
+            "m" => 60_000.0,
+            "h" => 3_600_000.0,
+            "d" => 86_400_000.0,
+            _ => return None,
+        };
+        total += num.parse::<f64>().ok()? * factor;
+        rest = tail.trim_start();
+    }
+    Some(total.round() as i64)
+}
+
+/// chrono panics when an invalid pattern is formatted, so it is checked first
+fn format_in<Tz: TimeZone>(tz: &Tz, time: i64, pattern: &str) -> Result<String>
+where
+    Tz::Offset: std::fmt::Display,
+{
+    let items: Vec<_> = StrftimeItems::new(pattern).collect();
+    rt_assert!(
+        !items.contains(&Item::Error),
+        "Invalid time pattern: {}",
+        pattern
+    );
+    let time = tz
+        .timestamp_millis_opt(time)
+        .single()
+        .ok_or_else(|| rt_err!("Invalid time: {}", time))?;
+    Ok(time.format_with_items(items.into_iter()).to_string())
+}
+
+fn parse_in<Tz: TimeZone>(tz: &Tz, s: &str, pattern: &str) -> Result<i64> {
+    if let Ok(time) = DateTime::parse_from_str(s, pattern) {
+        return Ok(time.timestamp_millis());
+    }
+    let naive = NaiveDateTime::parse_from_str(s, pattern).or_else(|e| {
+        NaiveDate::parse_from_str(s, pattern)
+            .map(|d| d.and_time(NaiveTime::MIN))
+            .map_err(|_| e)
+    });
+    let naive = naive.map_err(|e| rt_err!("Parsing {} as {}: {}", s, pattern, e))?;
+    let time = tz
+        .from_local_datetime(&naive)
+        .earliest()
+        .ok_or_else(|| rt_err!("{} doesn't exist in the local time zone", s))?;
+    Ok(time.timestamp_millis())
+}
+
+/// Styling is left out, if NO_COLOR is set, or neither stdout nor stderr is a terminal. Which of
+/// them the str is written to isn't known yet, [write_to] removes the styling again for a stream
+/// that isn't a terminal
+fn styled(content: StyledContent<&str>) -> String {
+    let no_color = std::env::var_os("NO_COLOR").is_some_and(|v| !v.is_empty());
+    let terminal = stream_is_terminal(Stream::Out) || stream_is_terminal(Stream::Err);
+    if terminal && !no_color {
+        content.to_string()
+    } else {
+        content.content().to_string()
+    }
+}
+
+/// removes the escape sequences that set colors and text attributes
+fn unstyled(s: &str) -> Cow<'_, str> {
+    if !s.contains('\x1b') {
+        return Cow::Borrowed(s);
+    }
+    let mut res = String::with_capacity(s.len());
+    let mut rest = s;
+    while let Some(start) = rest.find("\x1b[") {
+        res.push_str(&rest[..start]);
+        let params = &rest[start + 2..];
+        let params_len = params
+            .find(|c: char| !c.is_ascii_digit() && c != ';')
+            .unwrap_or(params.len());
+        if params[params_len..].starts_with('m') {
+            rest = &params[params_len + 1..];
+        } else {
+            // not styling, so it's kept
+            res.push_str(&rest[start..start + 2]);
+            rest = params;
+        }
+    }
+    res.push_str(rest);
+    Cow::Owned(res)
+}
+
+fn stream_is_terminal(stream: Stream) -> bool {
+    match stream {
+        Stream::In => std::io::stdin().is_terminal(),
+        Stream::Out => std::io::stdout().is_terminal(),
+        Stream::Err => std::io::stderr().is_terminal(),
+        Stream::Null | Stream::Capture => false,
+    }
+}
+
+/// The line of the spinner on stderr, see withSpinner. The spinner thread only draws while it
+/// holds the lock, and the script clears the line before it writes
+#[derive(Debug, Default)]
+pub struct SpinnerLine {
+    /// whether the line shows a frame of the spinner
+    drawn: bool,
+    /// set while a program runs in the foreground, no frames are drawn then
+    paused: bool,
+}
+
+static SPINNER_LINE: Mutex<SpinnerLine> = Mutex::new(SpinnerLine {
+    drawn: false,
+    paused: false,
+});
+
+fn spinner_line() -> MutexGuard<'static, SpinnerLine> {
+    // a thread that panicked while drawing can't leave the line in a broken state
+    SPINNER_LINE.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// clears the line of the spinner, if a frame is drawn. The line stays locked until the guard
+/// is dropped
+pub fn clear_spinner() -> MutexGuard<'static, SpinnerLine> {
+    let mut line = spinner_line();
+    if line.drawn {
+        eprint!("\r\x1b[2K");
+        line.drawn = false;
+    }
+    line
+}
+
+fn parse_color(name: &str) -> Result<Color> {
+    Color::try_from(name).map_err(|_| rt_err!("Unknown color: {}", name))
+}
+
+fn read_stdin_line() -> Result<Option<String>> {
+    let mut line = String::new();
+    let n = std::io::stdin()
+        .read_line(&mut line)
+        .map_err(|e| rt_err!("Reading a line from {}: {}", Stream::In, e))?;
+    let len = line.trim_end_matches(['\n', '\r']).len();
+    line.truncate(len);
+    Ok((n > 0).then_some(line))
+}
+
+/// writes the message to stderr, and reads the answer. Fails instead of waiting for input that
+/// no one can type
+fn ask(msg: &str) -> Result<String> {
+    rt_assert!(
+        std::io::stdin().is_terminal(),
+        "Can't ask for input, {} is not a terminal",
+        Stream::In
+    );
+    write_to(Stream::Err, msg)?;
+    read_stdin_line()?.ok_or_else(|| rt_err!("No answer, {} was closed", Stream::In))
+}
+
+/// the values are separated by spaces, like the arguments of echo
+fn print_to(stream: Stream, values: Vec<AnyData>, end: &str) -> Result<()> {
+    let strs: Vec<String> = values.iter().map(|v| v.0.to_string()).collect();
+    write_to(stream, &(strs.join(" ") + end))
+}
+
+/// tries names in the temp dir until `create` doesn't find an existing file
+fn create_tmp_path(create: impl Fn(&Path) -> std::io::Result<()>) -> Result<String> {
+    let nanos = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.subsec_nanos())
+        .unwrap_or_default();
+    for i in 0.. {
+        let name = format!("levis-{}-{:x}{}", process::id(), nanos, i);
+        let path = std::env::temp_dir().join(name);
+        match create(&path) {
+            Ok(()) => return path_to_string(path),
+            Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => continue,
+            Err(e) => return Err(rt_err!("Creating {}: {}", path.display(), e)),
+        }
+    }
+    unreachable!()
+}
+
+/// std::env panics on names and values that can't be put into the environment
+fn check_env_var(name: &str, value: &str) -> Result<()> {
+    rt_assert!(
+        !name.is_empty() && !name.contains(['=', '\0']) && !value.contains('\0'),
+        "Invalid environment variable: {}",
+        name
+    );
+    Ok(())
+}
+
+pub fn env_value(name: &str, value: OsString) -> Result<String> {
+    os_to_string(value, &format!("Environment variable {}", name))
+}
+
+fn path_to_string(path: PathBuf) -> Result<String> {
+    os_to_string(path.into_os_string(), "Path")
+}
+
+/// Strs are UTF-8, but paths and environment variables don't need to be. Those that aren't are
+/// rejected instead of being changed silently. `what` starts the message
+pub fn os_to_string(s: OsString, what: &str) -> Result<String> {
+    s.into_string()
+        .map_err(|s| rt_err!("{} is not valid UTF-8: {}", what, s.to_string_lossy()))
+}
+
+/// a part of a path that was a str. Splitting a str at a `/` leaves valid UTF-8
+fn os_str_part(part: Option<&OsStr>) -> String {
+    part.and_then(OsStr::to_str).unwrap_or_default().into()
+}
+
+fn capture_list(caps: &regex::Captures) -> Vec<String> {
+    caps.iter()
+        .map(|m| m.map(|m| m.as_str()).unwrap_or_default().into())
+        .collect()
+}
+
+fn slice_range(start: i64, end: i64, len: usize) -> Result<(usize, usize)> {
+    rt_assert!(
+        0 <= start && start <= end && end <= len as i64,
+        "Invalid range {}..{} for a length of {}",
+        start,
+        end,
+        len
+    );
+    Ok((start as usize, end as usize))
+}
+
 pub unsafe fn wrapper_0_var_ret<R, T1>(
     f: unsafe fn(Vec<T1>) -> Result<R>,
     _: &str,
@@ -71,18 +2365,7 @@ where
     T1: TryFromRef<RuntimeData> + Debug,
     R: Into<Storable> + Debug,
 {
-    let n = mem.stack_back(0);
-    let n = get_data_as::<i64>(n)? as usize;
-    let mut args: Vec<T1> = Vec::with_capacity(n);
-    args.set_len(n);
-    for i in 0..n {
-        // you might think this doesn't work because we kill the object we get a ref from
-        // but it's either a ref, in which case the actual value lives somewhere else, and the
-        // ref is copy. Or it's a primitive value, which is copy anyway
-        let arg = mem.stack_back(i + 1);
-        let val = get_data_as::<T1>(arg)?;
-        *args.get_unchecked_mut(n - 1 - i) = val;
-    }
+    let args = get_var_args(mem)?;
     let s_entry = f(args)?.into();
     mem.store(s_entry);
     Ok(())
@@ -97,25 +2380,156 @@ where
     T1: TryFromRef<RuntimeData>,
     T2: TryFromRef<RuntimeData>,
 {
-    let n = mem.stack_back(0);
-    let n = get_data_as::<i64>(n)? as usize;
-    let mut args: Vec<T2> = Vec::with_capacity(n);
-    args.set_len(n);
-    for i in 0..n {
-        // you might think this doesn't work because we kill the object we get a ref from
-        // but it's either a ref, in which case the actual value lives somewhere else, and the
-        // ref is copy. Or it's a primitive value, which is copy anyway
-        let arg = mem.stack_back(i + 1);
-        let val = get_data_as::<T2>(arg)?;
-        *args.get_unchecked_mut(n - 1 - i) = val;
-    }
-    let d1 = mem.stack_back(n + 1);
+    let args: Vec<T2> = get_var_args(mem)?;
+    let d1 = mem.stack_back(args.len() + 1);
     let a1 = get_data_as(d1)?;
     f(a1, args)?;
     mem.store(CopyValue::Unit.into());
     Ok(())
 }
 
+pub unsafe fn wrapper_mem_1_var_ret<R, T1, T2>(
+    f: unsafe fn(&mut Memory, T1, Vec<T2>) -> Result<R>,
+    _: &str,
+    mem: &mut Memory,
+) -> Result<()>
+where
+    T1: TryFromRef<RuntimeData>,
+    T2: TryFromRef<RuntimeData>,
+    R: Into<Storable>,
+{
+    let args: Vec<T2> = get_var_args(mem)?;
+    let d1 = mem.stack_back(args.len() + 1);
+    let a1 = get_data_as(d1)?;
+    let s_entry = f(mem, a1, args)?.into();
+    mem.store(s_entry);
+    Ok(())
+}
+
+/// for the redirected program calls, which have the targets of the 3 streams before the program
+pub unsafe fn wrapper_4_var<T1, T2, T3, T4, T5>(
+    f: unsafe fn(T1, T2, T3, T4, Vec<T5>) -> Result<()>,
+    _: &str,
+    mem: &mut Memory,
+) -> Result<()>
+where
+    T1: TryFromRef<RuntimeData>,
+    T2: TryFromRef<RuntimeData>,
+    T3: TryFromRef<RuntimeData>,
+    T4: TryFromRef<RuntimeData>,
+    T5: TryFromRef<RuntimeData>,
+{
+    let args: Vec<T5> = get_var_args(mem)?;
+    let n = args.len();
+    let a1 = get_data_as(mem.stack_back(n + 4))?;
+    let a2 = get_data_as(mem.stack_back(n + 3))?;
+    let a3 = get_data_as(mem.stack_back(n + 2))?;
+    let a4 = get_data_as(mem.stack_back(n + 1))?;
+    f(a1, a2, a3, a4, args)?;
+    mem.store(CopyValue::Unit.into());
+    Ok(())
+}
+
+type MemFn4Var<R, T1, T2, T3, T4, T5> =
+    unsafe fn(&mut Memory, T1, T2, T3, T4, Vec<T5>) -> Result<R>;
+
+pub unsafe fn wrapper_mem_4_var_ret<R, T1, T2, T3, T4, T5>(
+    f: MemFn4Var<R, T1, T2, T3, T4, T5>,
+    _: &str,
+    mem: &mut Memory,
+) -> Result<()>
+where
+    T1: TryFromRef<RuntimeData>,
+    T2: TryFromRef<RuntimeData>,
+    T3: TryFromRef<RuntimeData>,
+    T4: TryFromRef<RuntimeData>,
+    T5: TryFromRef<RuntimeData>,
+    R: Into<Storable>,
+{
+    let args: Vec<T5> = get_var_args(mem)?;
+    let n = args.len();
+    let a1 = get_data_as(mem.stack_back(n + 4))?;
+    let a2 = get_data_as(mem.stack_back(n + 3))?;
+    let a3 = get_data_as(mem.stack_back(n + 2))?;
+    let a4 = get_data_as(mem.stack_back(n + 1))?;
+    let s_entry = f(mem, a1, a2, a3, a4, args)?.into();
+    mem.store(s_entry);
+    Ok(())
+}
+
+/// generates wrappers for built ins with a fixed number of arguments.
+///
+/// The `mem` variants pass the memory to the built in as first argument.
+macro_rules! fixed_arity_wrappers {
+    ($(($name:ident, $mem_name:ident, $n:literal $(, $t:ident $a:ident)*)),* $(,)?) => {
+        $(
+            pub unsafe fn $name<R $(, $t)*>(
+                f: unsafe fn($($t),*) -> Result<R>,
+                _: &str,
+                mem: &mut Memory,
+            ) -> Result<()>
+            where
+                $($t: TryFromRef<RuntimeData>,)*
+                R: Into<Storable>,
+            {
+                let mut _ridx: usize = $n;
+                $(
+                    _ridx -= 1;
+                    let $a: $t = get_data_as(mem.stack_back(_ridx))?;
+                )*
+                let s_entry = f($($a),*)?.into();
+                mem.store(s_entry);
+                Ok(())
+            }
+
+            pub unsafe fn $mem_name<R $(, $t)*>(
+                f: unsafe fn(&mut Memory $(, $t)*) -> Result<R>,
+                _: &str,
+                mem: &mut Memory,
+            ) -> Result<()>
+            where
+                $($t: TryFromRef<RuntimeData>,)*
+                R: Into<Storable>,
+            {
+                let mut _ridx: usize = $n;
+                $(
+                    _ridx -= 1;
+                    let $a: $t = get_data_as(mem.stack_back(_ridx))?;
+                )*
+                let s_entry = f(mem $(, $a)*)?.into();
+                mem.store(s_entry);
+                Ok(())
+            }
+        )*
+    };
+}
+
+fixed_arity_wrappers! {
+    (wrapper_0_ret, wrapper_mem_0_ret, 0),
+    (wrapper_1_ret, wrapper_mem_1_ret, 1, T1 a1),
+    (wrapper_2_ret, wrapper_mem_2_ret, 2, T1 a1, T2 a2),
+    (wrapper_3_ret, wrapper_mem_3_ret, 3, T1 a1, T2 a2, T3 a3),
+}
+
+/// reads the variadic arguments of a built in from the stack.
+///
+/// The stack top contains the number of variadic arguments, and below it are the arguments
+/// themselfes
+unsafe fn get_var_args<T>(mem: &Memory) -> Result<Vec<T>>
+where
+    T: TryFromRef<RuntimeData>,
+{
+    let n = mem.stack_back(0);
+    let n = get_data_as::<i64>(n)? as usize;
+    // you might think this doesn't work because we kill the object we get a ref from
+    // but it's either a ref, in which case the actual value lives somewhere else, and the
+    // ref is copy. Or it's a primitive value, which is copy anyway
+    (0..n)
+        .rev()
+        .map(|i| get_data_as::<T>(mem.stack_back(i + 1)))
+        .collect()
+}
+
 unsafe fn get_data_as<T>(d: &RuntimeData) -> Result<T>
 where
     T: TryFromRef<RuntimeData>,
@@ -0,0 +1,56 @@
+//! The globals that are available in every script
+//!
+//! FILE and LINE are known at compile time, so the compiler puts FILE into the data section, and
+//! LINE, the line it's used in, onto the stack. The values of the other globals are created by
+//! [OpCode::PushGlobal] whenever they are used.
+
+use crate::vm::*;
+
+pub const GLOBALS: [&str; 9] = [
+    "FILE", "LINE", "ARGV", "ENV", "IN", "OUT", "ERR", "NULL", "CAPTURE",
+];
+
+pub fn global_type(name: &str) -> Option<DataType> {
+    Some(match name {
+        "FILE" => DataType::str(),
+        "LINE" => DataType::int(),
+        "ARGV" => DataType::str().vec(),
+        "ENV" => DataType::dict(DataType::str(), DataType::str()),
+        "IN" | "OUT" | "ERR" | "NULL" | "CAPTURE" => DataType::stream(),
+        _ => return None,
+    })
+}
+
+/// the globals that are created at runtime
+pub fn global(name: &str) -> Option<Global> {
+    Some(match name {
+        "ARGV" => Global::Argv,
+        "ENV" => Global::Env,
+        "IN" => Global::In,
+        "OUT" => Global::Out,
+        "ERR" => Global::Err,
+        "NULL" => Global::Null,
+        "CAPTURE" => Global::Capture,
+        _ => return None,
+    })
+}
+
+pub fn value(global: Global, mem: &Memory) -> Result<Storable> {
+    Ok(match global {
+        Global::Argv => mem.argv.clone().into(),
+        // a snapshot. Like paths, names or values that aren't valid UTF-8 are an error
+        Global::Env => std::env::vars_os()
+            .map(|(k, v)| {
+                let k = built_ins::os_to_string(k, "The name of an environment variable")?;
+                let v = built_ins::env_value(&k, v)?;
+                Ok((k, v))
+            })
+            .collect::<Result<Vec<_>>>()?
+            .into(),
+        Global::In => Stream::In.into(),
+        Global::Out => Stream::Out.into(),
+        Global::Err => Stream::Err.into(),
+        Global::Null => Stream::Null.into(),
+        Global::Capture => Stream::Capture.into(),
+    })
+}
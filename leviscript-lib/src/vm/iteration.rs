@@ -0,0 +1,311 @@
+//! The state of the higher order built ins, like map, while their function is evaluated.
+//!
+//! A call of a higher order built in is compiled into a loop, the body of the fragment that is
+//! passed to it is the body of the loop. The built in starts an [Iteration] over the list, then
+//! for each element:
+//! - [OpCode::IterNext] pushes the arguments of the fragment, or jumps behind the loop, once
+//!   there are no elements left
+//! - [OpCode::IterCollect] hands the result of the body to the iteration
+//!
+//! Then [OpCode::IterEnd] replaces the iteration by the result of the call. The fragment gets
+//! copies of the elements, and the iteration copies what it keeps, so the loop doesn't need to
+//! care about who owns what.
+//!
+//! Most of them also iterate over lazy sequences, then each element is produced right before the
+//! fragment gets it, and any and all stop consuming the sequence once their result is known. map,
+//! filter and mapIndexed make a lazy sequence instead, see [crate::vm::sequence::Fragment].
+
+use crate::core::*;
+use crate::vm::{rt_err, Error, Memory, Result, Storable};
+
+use ordered_float::OrderedFloat;
+use std::collections::BTreeSet;
+
+/// what the higher order built in does with the results of its function
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum IterKind {
+    Map,
+    Filter,
+    Fold,
+    Each,
+    Any,
+    All,
+    SortBy,
+    MapIndexed,
+}
+
+#[derive(Debug)]
+pub struct Iteration {
+    kind: IterKind,
+    source: Source,
+    /// the index of the next element
+    next: usize,
+    /// the results of map, the elements that passed filter, or the accumulator of fold. They
+    /// are owned by the iteration
+    collected: Vec<RuntimeData>,
+    /// the results of the function of sortBy, by the index of the element
+    keys: Vec<SortKey>,
+    /// set by any and all, once the result is known
+    done: bool,
+}
+
+/// what is iterated over. It stays on the stack until the iteration ended
+#[derive(Debug)]
+enum Source {
+    /// the elements of a list
+    List(Vec<RuntimeData>),
+    /// the index of a sequence
+    Seq(usize),
+}
+
+/// A copy of a value that can be ordered. Values of the same type are compared by their
+/// content, lists element by element
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum SortKey {
+    Unit,
+    Bool(bool),
+    Int(i64),
+    Float(OrderedFloat<f64>),
+    Str(String),
+    List(Vec<SortKey>),
+    /// values without a natural order are compared by their text
+    Other(String),
+}
+
+/// starts an iteration over the list or sequence on top of the stack. Fold's initial value is
+/// below it
+pub unsafe fn start(mem: &mut Memory, kind: IterKind) -> Result<()> {
+    let top = mem.stack_back(0);
+    let source = match top.rtry_into() {
+        Some(SeqData(idx)) => Source::Seq(idx),
+        None => Source::List(expect_list(top)?),
+    };
+    let collected = if kind == IterKind::Fold {
+        let init = copy(mem.stack_back(1))?;
+        vec![mem.alloc(init)]
+    } else {
+        vec![]
+    };
+    mem.iterations.push(Iteration {
+        kind,
+        source,
+        next: 0,
+        collected,
+        keys: vec![],
+        done: false,
+    });
+    Ok(())
+}
+
+/// Pushes the arguments of the fragment for the next element, and returns whether there was one.
+///
+/// The element comes last, fold passes the accumulator before it, and mapIndexed the index
+pub unsafe fn next(mem: &mut Memory) -> Result<bool> {
+    let it = innermost(mem)?;
+    if it.done {
+        return Ok(false);
+    }
+    let idx = it.next;
+    let kind = it.kind;
+    let elem = match &it.source {
+        Source::List(elems) => match elems.get(idx) {
+            Some(elem) => copy(elem)?,
+            None => return Ok(false),
+        },
+        Source::Seq(seq_idx) => {
+            let seq_idx = *seq_idx;
+            match mem.next_elem(seq_idx)? {
+                Some(elem) => elem,
+                None => return Ok(false),
+            }
+        }
+    };
+    let it = innermost(mem)?;
+    it.next += 1;
+    match kind {
+        IterKind::Fold => {
+            let acc = copy(&it.collected[0])?;
+            mem.store(acc);
+        }
+        IterKind::MapIndexed => mem.store((idx as i64).into()),
+        _ => {}
+    }
+    mem.store(elem);
+    Ok(true)
+}
+
+/// hands the result of the fragment on top of the stack to the iteration. The element is right
+/// below it. Nothing is popped
+pub unsafe fn collect(mem: &mut Memory) -> Result<()> {
+    let result = mem.stack_back(0).clone();
+    let elem = mem.stack_back(1).clone();
+    let kind = innermost(mem)?.kind;
+    let is_true = matches!(result, Data::CopyVal(CopyValue::Bool(true)));
+    match kind {
+        IterKind::Map | IterKind::MapIndexed => {
+            let copied = mem.alloc(copy(&result)?);
+            innermost(mem)?.collected.push(copied);
+        }
+        IterKind::Filter if is_true => {
+            let copied = mem.alloc(copy(&elem)?);
+            innermost(mem)?.collected.push(copied);
+        }
+        IterKind::Fold => {
+            let copied = mem.alloc(copy(&result)?);
+            let old = std::mem::replace(&mut innermost(mem)?.collected[0], copied);
+            mem.free(old);
+        }
+        IterKind::SortBy => {
+            let key = sort_key(&result);
+            innermost(mem)?.keys.push(key);
+        }
+        IterKind::Any if is_true => innermost(mem)?.done = true,
+        IterKind::All if !is_true => innermost(mem)?.done = true,
+        IterKind::Filter | IterKind::Each | IterKind::Any | IterKind::All => {}
+    }
+    Ok(())
+}
+
+/// ends the innermost iteration, and pushes the result of the higher order built in
+pub unsafe fn finish(mem: &mut Memory) -> Result<()> {
+    let it = mem
+        .iterations
+        .pop()
+        .ok_or_else(|| rt_err!("Ended an iteration that was never started"))?;
+    match it.kind {
+        // the list takes over the ownership of the collected values
+        IterKind::Map | IterKind::MapIndexed | IterKind::Filter => {
+            mem.push_heap(Value::Vec(it.collected.into_iter().collect()))
+        }
+        IterKind::Fold => mem.stack.extend(it.collected),
+        IterKind::Each => mem.push_stack(CopyValue::Unit),
+        IterKind::Any => mem.push_stack(it.done),
+        IterKind::All => mem.push_stack(!it.done),
+        IterKind::SortBy => {
+            let Source::List(elems) = it.source else {
+                return Err(rt_err!("sortBy can only sort lists"));
+            };
+            let mut order: Vec<usize> = (0..it.keys.len()).collect();
+            // the sort is stable, equal elements keep their order
+            order.sort_by(|a, b| it.keys[*a].cmp(&it.keys[*b]));
+            let sorted = order
+                .into_iter()
+                .map(|i| copy(&elems[i]))
+                .collect::<Result<_>>()?;
+            mem.store(Storable::VecOnHeap(sorted));
+        }
+    }
+    Ok(())
+}
+
+/// the elements of a list, they stay owned by it
+pub unsafe fn list_elems(d: &RuntimeData) -> Option<Vec<RuntimeData>> {
+    match d {
+        Data::Ref(RuntimeRef::HeapRef(addr)) => match &**addr {
+            Value::Vec(v) => Some(v.iter().cloned().collect()),
+            _ => None,
+        },
+        Data::Ref(RuntimeRef::DataSecRef(addr)) => match &**addr {
+            // data section values can't contain refs, there's nothing to point to
+            Value::Vec(v) => v
+                .iter()
+                .map(|e| match e {
+                    Data::CopyVal(c) => Some(Data::CopyVal(*c)),
+                    Data::Ref(_) => None,
+                })
+                .collect(),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+pub unsafe fn expect_list(d: &RuntimeData) -> Result<Vec<RuntimeData>> {
+    list_elems(d).ok_or_else(|| rt_err!("Expected a list, found: {}", d))
+}
+
+/// a deep copy, which doesn't share any heap values with the original
+pub unsafe fn copy(d: &RuntimeData) -> Result<Storable> {
+    match d {
+        Data::CopyVal(v) => Ok(Storable::OnStack(Data::CopyVal(*v))),
+        Data::Ref(RuntimeRef::HeapRef(p)) => copy_value(&**p, |e| copy(e)),
+        Data::Ref(RuntimeRef::DataSecRef(p)) => copy_value(&**p, |e| match e {
+            Data::CopyVal(v) => Ok(Storable::OnStack(Data::CopyVal(*v))),
+            Data::Ref(_) => Err(rt_err!("Found a ref in the data section")),
+        }),
+    }
+}
+
+pub unsafe fn sort_key(d: &RuntimeData) -> SortKey {
+    match d {
+        Data::CopyVal(v) => copy_value_key(v),
+        Data::Ref(RuntimeRef::HeapRef(p)) => value_key(&**p, |e| sort_key(e)),
+        Data::Ref(RuntimeRef::DataSecRef(p)) => value_key(&**p, |e| match e {
+            Data::CopyVal(v) => copy_value_key(v),
+            Data::Ref(_) => SortKey::Unit,
+        }),
+    }
+}
+
+/// the elements without duplicates, the first occurrence of each one is kept
+pub unsafe fn unique(elems: &[RuntimeData]) -> Vec<&RuntimeData> {
+    let mut seen = BTreeSet::new();
+    elems.iter().filter(|e| seen.insert(sort_key(e))).collect()
+}
+
+fn innermost(mem: &mut Memory) -> Result<&mut Iteration> {
+    mem.iterations
+        .last_mut()
+        .ok_or_else(|| rt_err!("Continued an iteration that was never started"))
+}
+
+fn copy_value<RefT: RefRequirements>(
+    value: &Value<RefT>,
+    copy_elem: impl Fn(&Data<RefT>) -> Result<Storable>,
+) -> Result<Storable> {
+    let copy_all = |elems: &mut dyn Iterator<Item = &Data<RefT>>| {
+        elems.map(&copy_elem).collect::<Result<Vec<_>>>()
+    };
+    Ok(match value {
+        Value::Str(s) => Storable::OnHeap(Value::Str(s.clone())),
+        Value::Keyword(s) => Storable::OnHeap(Value::Keyword(s.clone())),
+        Value::Regex(s) => Storable::OnHeap(Value::Regex(s.clone())),
+        Value::Vec(v) => Storable::VecOnHeap(copy_all(&mut v.iter())?),
+        Value::Set(s) => Storable::SetOnHeap(copy_all(&mut s.iter())?),
+        Value::Dict(d) => Storable::DictOnHeap(
+            d.iter()
+                .map(|(k, v)| Ok((copy_elem(k)?, copy_elem(v)?)))
+                .collect::<Result<_>>()?,
+        ),
+        Value::Json(d) => Storable::JsonOnHeap(Box::new(copy_elem(d)?)),
+        Value::Opt(d) => Storable::OptOnHeap(d.as_ref().map(&copy_elem).transpose()?.map(Box::new)),
+        Value::Seq(_) => {
+            return Err(rt_err!(
+                "Sequences can't be copied, they can only be consumed once"
+            ))
+        }
+    })
+}
+
+fn copy_value_key(v: &CopyValue) -> SortKey {
+    match v {
+        CopyValue::Unit => SortKey::Unit,
+        CopyValue::Bool(b) => SortKey::Bool(*b),
+        CopyValue::Int(i) => SortKey::Int(*i),
+        CopyValue::Float(f) => SortKey::Float(*f),
+        CopyValue::ProcHandle(_) | CopyValue::Stream(_) => SortKey::Other(v.to_string()),
+    }
+}
+
+fn value_key<RefT: RefRequirements + std::fmt::Display>(
+    value: &Value<RefT>,
+    elem_key: impl Fn(&Data<RefT>) -> SortKey,
+) -> SortKey {
+    match value {
+        Value::Str(s) | Value::Keyword(s) | Value::Regex(s) => SortKey::Str(s.clone()),
+        Value::Vec(v) => SortKey::List(v.iter().map(elem_key).collect()),
+        Value::Json(d) | Value::Opt(Some(d)) => elem_key(d),
+        Value::Opt(None) => SortKey::Unit,
+        Value::Set(_) | Value::Dict(_) | Value::Seq(_) => SortKey::Other(value.to_string()),
+    }
+}
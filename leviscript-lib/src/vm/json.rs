@@ -0,0 +1,203 @@
+//! Converts between run-time data and json, for the json built ins.
+//!
+//! Parsed json becomes nested values, objects become dicts with str keys and arrays become vecs.
+//! The result is wrapped in a [Value::Json], which gives it the dynamic json type.
+//!
+//! A json value is narrowed to a static type by accessing it: jsonStr, jsonInt, jsonFloat,
+//! jsonBool and jsonList give an optional of their type, which is none if the value has
+//! another type. jsonGet, jsonAt and jsonKeys fail if the value isn't a dict or a list.
+
+use crate::core::*;
+use crate::vm::{rt_err, Error, Result, Storable};
+
+use serde_json::{Map, Number, Value as Json};
+
+/// fails with the byte offset of the first error
+pub fn parse(s: &str) -> Result<Storable> {
+    let json: Json = serde_json::from_str(s)
+        .map_err(|e| rt_err!("Invalid json at byte {}: {}", byte_offset(s, &e), e))?;
+    Ok(Storable::JsonOnHeap(Box::new(to_storable(json))))
+}
+
+pub fn to_storable(json: Json) -> Storable {
+    match json {
+        Json::Null => ().into(),
+        Json::Bool(b) => b.into(),
+        Json::Number(n) => match n.as_i64() {
+            Some(i) => i.into(),
+            // numbers that don't fit an int, are stored as floats
+            None => n.as_f64().unwrap_or(f64::NAN).into(),
+        },
+        Json::String(s) => s.into(),
+        Json::Array(elems) => Storable::VecOnHeap(elems.into_iter().map(to_storable).collect()),
+        Json::Object(entries) => Storable::DictOnHeap(
+            entries
+                .into_iter()
+                .map(|(k, v)| (k.into(), to_storable(v)))
+                .collect(),
+        ),
+    }
+}
+
+/// Strs, keywords and regexes become strings, vecs and sets become arrays. Dict keys that aren't
+/// strings are converted to their json text
+pub unsafe fn from_data(d: &RuntimeData) -> Result<Json> {
+    match d {
+        Data::CopyVal(v) => from_copy_value(v),
+        Data::Ref(RuntimeRef::HeapRef(p)) => from_value(&**p, |d| from_data(d)),
+        Data::Ref(RuntimeRef::DataSecRef(p)) => from_value(&**p, from_comptime_data),
+    }
+}
+
+/// the name of the json type of the data, as returned by jsonType
+pub unsafe fn type_name(d: &RuntimeData) -> &'static str {
+    let value = match d {
+        Data::CopyVal(CopyValue::Unit) => return "null",
+        Data::CopyVal(CopyValue::Bool(_)) => return "bool",
+        Data::CopyVal(CopyValue::Int(_)) => return "int",
+        Data::CopyVal(CopyValue::Float(_)) => return "float",
+        Data::CopyVal(_) => return "unknown",
+        Data::Ref(RuntimeRef::HeapRef(p)) => &**p,
+        Data::Ref(RuntimeRef::DataSecRef(_)) => return "str",
+    };
+    match value {
+        Value::Str(_) | Value::Keyword(_) | Value::Regex(_) => "str",
+        Value::Vec(_) | Value::Set(_) => "list",
+        Value::Dict(_) => "dict",
+        Value::Json(d) | Value::Opt(Some(d)) => type_name(d),
+        Value::Opt(None) => "null",
+        Value::Seq(_) => "unknown",
+    }
+}
+
+/// the element of a json list, copied into a new json value
+pub unsafe fn at(d: &RuntimeData, idx: i64) -> Result<Storable> {
+    let Some(Value::Vec(v)) = heap_value(d) else {
+        return Err(type_err(d, "list"));
+    };
+    let elem = usize::try_from(idx)
+        .ok()
+        .and_then(|i| v.get(i))
+        .ok_or_else(|| {
+            rt_err!(
+                "Index {} out of range for a json list of length {}",
+                idx,
+                v.len()
+            )
+        })?;
+    copy(elem)
+}
+
+/// the value of a key of a json dict, copied into a new json value
+pub unsafe fn get(d: &RuntimeData, key: &str) -> Result<Storable> {
+    let Some(Value::Dict(entries)) = heap_value(d) else {
+        return Err(type_err(d, "dict"));
+    };
+    // the keys are refs, so they can't be looked up by their content
+    let value = entries
+        .iter()
+        .find(|(k, _)| str_key(k) == Some(key))
+        .map(|(_, v)| v)
+        .ok_or_else(|| rt_err!("Key not found in json dict: {}", key))?;
+    copy(value)
+}
+
+/// the elements of a json list, each one copied into a new json value
+pub unsafe fn elems(d: &RuntimeData) -> Result<Vec<Storable>> {
+    let Some(Value::Vec(v)) = heap_value(d) else {
+        return Err(type_err(d, "list"));
+    };
+    v.iter().map(|e| copy(e)).collect()
+}
+
+/// the keys of a json dict, sorted
+pub unsafe fn keys(d: &RuntimeData) -> Result<Vec<String>> {
+    let Some(Value::Dict(entries)) = heap_value(d) else {
+        return Err(type_err(d, "dict"));
+    };
+    let mut keys: Vec<String> = entries
+        .keys()
+        .map(|k| str_key(k).map(String::from))
+        .collect::<Option<_>>()
+        .ok_or_else(|| rt_err!("Found a json dict with keys that aren't strs"))?;
+    keys.sort();
+    Ok(keys)
+}
+
+/// the error for json data that doesn't have the expected type
+unsafe fn type_err(d: &RuntimeData, expected: &str) -> Error {
+    rt_err!("Expected a json {}, found {}", expected, type_name(d))
+}
+
+unsafe fn copy(d: &RuntimeData) -> Result<Storable> {
+    Ok(Storable::JsonOnHeap(Box::new(to_storable(from_data(d)?))))
+}
+
+unsafe fn str_key<'a>(k: &RuntimeData) -> Option<&'a str> {
+    let s: *const String = k.rtry_into()?;
+    Some((*s).as_str())
+}
+
+unsafe fn heap_value<'a>(d: &RuntimeData) -> Option<&'a RuntimeValue> {
+    match d {
+        Data::Ref(RuntimeRef::HeapRef(p)) => Some(&**p),
+        _ => None,
+    }
+}
+
+fn from_copy_value(v: &CopyValue) -> Result<Json> {
+    Ok(match v {
+        CopyValue::Int(i) => (*i).into(),
+        CopyValue::Float(f) => Number::from_f64(f.0)
+            .map(Json::Number)
+            .ok_or_else(|| rt_err!("Can't convert {} to json", f))?,
+        CopyValue::Bool(b) => (*b).into(),
+        CopyValue::Unit => Json::Null,
+        CopyValue::ProcHandle(_) | CopyValue::Stream(_) => {
+            return Err(rt_err!("Can't convert {} to json", v))
+        }
+    })
+}
+
+// the elements of a container in the data section can't be refs
+fn from_comptime_data(d: &ComptimeData) -> Result<Json> {
+    match d {
+        Data::CopyVal(v) => from_copy_value(v),
+        Data::Ref(_) => Err(rt_err!("Found a ref in the data section")),
+    }
+}
+
+fn from_value<RefT: RefRequirements>(
+    value: &Value<RefT>,
+    from_elem: impl Fn(&Data<RefT>) -> Result<Json>,
+) -> Result<Json> {
+    Ok(match value {
+        Value::Str(s) | Value::Keyword(s) | Value::Regex(s) => s.clone().into(),
+        Value::Vec(v) => Json::Array(v.iter().map(&from_elem).collect::<Result<_>>()?),
+        Value::Set(s) => Json::Array(s.iter().map(&from_elem).collect::<Result<_>>()?),
+        Value::Dict(d) => {
+            let mut entries = Map::new();
+            for (k, v) in d {
+                let key = match from_elem(k)? {
+                    Json::String(s) => s,
+                    other => other.to_string(),
+                };
+                entries.insert(key, from_elem(v)?);
+            }
+            Json::Object(entries)
+        }
+        Value::Json(d) | Value::Opt(Some(d)) => from_elem(d)?,
+        Value::Opt(None) => Json::Null,
+        Value::Seq(_) => return Err(rt_err!("Can't convert a sequence to json")),
+    })
+}
+
+/// serde_json reports lines and columns, both start at 1, and columns count bytes
+fn byte_offset(s: &str, e: &serde_json::Error) -> usize {
+    let line_start: usize = s
+        .split_inclusive('\n')
+        .take(e.line().saturating_sub(1))
+        .map(str::len)
+        .sum();
+    line_start + e.column().saturating_sub(1)
+}
@@ -1,19 +1,104 @@
 use crate::core::*;
-use crate::vm::Heap;
+use crate::vm::{iteration, rt_err, Error, Heap, Iteration, Result, Sequence};
+
+use regex::Regex;
+use std::collections::HashMap;
+use std::ffi::OsString;
+use std::io::Read;
+use std::path::PathBuf;
+use std::process::Child;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::Instant;
 
 pub type Stack = Vec<RuntimeData>;
 /// Represents the 3 relevant memory areas of the VM: Heap, Stack, and Data segment
+///
+/// Additionally, it owns the processes that were started in the background. When the memory is
+/// dropped, all of them that are still running are killed, and all of them are reaped.
 #[derive(Default, Debug)]
 pub struct Memory {
     pub stack: Stack,
     pub heap: Heap<RuntimeValue>,
     pub data_seg: Vec<ComptimeValue>,
     pub registers: [RuntimeData; 1],
+    /// processes started in the background, a [ProcHandle] is an index into this vec
+    pub children: Vec<BgProcess>,
+    /// the arguments of the script, the first one is the name of the script itself
+    pub args: Vec<String>,
+    /// the arguments as they were passed, for ARGV. Selecting a mode changes args
+    pub argv: Vec<String>,
+    /// the main sections of the script, see [OpCode::ParseArgs]
+    pub arg_specs: Vec<ArgSpec>,
+    /// the modes sections of the script, see [OpCode::SelectMode]
+    pub mode_specs: Vec<ModesSpec>,
+    /// the index of the mode that was selected by [OpCode::SelectMode]
+    pub selected_mode: usize,
+    /// the compiled regexes by their pattern, so each one is compiled only once
+    pub regexes: HashMap<String, Regex>,
+    /// the changes made by the scoped built ins that are currently running, innermost last
+    pub scope_guards: Vec<ScopeGuard>,
+    /// the higher order built ins that are currently running, innermost last
+    pub iterations: Vec<Iteration>,
+    /// the state of the lazy sequences, a [Value::Seq] is an index into this vec. The state is
+    /// removed, when the sequence is freed or consumed by another one
+    pub sequences: Vec<Option<Sequence>>,
+    /// where the frame of the running fragment of a lazy sequence starts, stack indices in
+    /// opcodes are relative to it. 0 outside of them, see [crate::vm::sequence::Fragment]
+    pub frame_base: usize,
+}
+
+/// Undoes what a scoped built in changed, when its body is left. See [OpCode::LeaveScope]
+#[derive(Debug)]
+pub enum ScopeGuard {
+    /// the name of an environment variable, and its previous value, if it was set
+    Env(String, Option<OsString>),
+    /// the previous working directory
+    Cwd(PathBuf),
+    /// a temporary file or directory, which is deleted
+    TmpPath(PathBuf),
+    /// the thread that animates a spinner, it stops when the flag is set
+    Spinner(Arc<AtomicBool>, JoinHandle<()>),
+    /// when the measured body was entered, see [OpCode::LeaveMeasure]
+    Measure(Instant),
+    /// the built in didn't need to change anything
+    Nothing,
+}
+
+/// A process started in the background. Its stdout and stderr are collected by threads while it
+/// runs, so it never blocks on a full pipe
+#[derive(Debug)]
+pub struct BgProcess {
+    pub child: Child,
+    stdout: Captured,
+    stderr: Captured,
+}
+
+#[derive(Debug)]
+struct Captured {
+    /// the thread that reads the stream, until it is joined
+    reader: Option<JoinHandle<Vec<u8>>>,
+    output: String,
 }
 
 pub enum Storable {
     OnHeap(RuntimeValue),
     OnStack(RuntimeData),
+    /// Becomes a Vec of Strs. Each str is put on the heap separately, and is owned by the vec
+    StrVecOnHeap(Vec<String>),
+    /// Becomes a Vec, the elements are stored first, and are owned by the vec
+    VecOnHeap(Vec<Storable>),
+    /// Becomes a Set, the elements are owned by the set
+    SetOnHeap(Vec<Storable>),
+    /// Becomes a Dict, the keys and values are owned by the dict
+    DictOnHeap(Vec<(Storable, Storable)>),
+    /// Becomes a json value, which owns the wrapped value
+    JsonOnHeap(Box<Storable>),
+    /// Becomes a Seq, its state is kept by the memory
+    SeqOnHeap(Sequence),
+    /// Becomes an optional value, which owns the wrapped value
+    OptOnHeap(Option<Box<Storable>>),
 }
 
 impl From<Vec<ComptimeValue>> for Memory {
@@ -23,6 +108,17 @@ impl From<Vec<ComptimeValue>> for Memory {
             heap: Heap::new(),
             data_seg: value,
             registers: Default::default(),
+            children: vec![],
+            args: vec![],
+            argv: vec![],
+            arg_specs: vec![],
+            mode_specs: vec![],
+            selected_mode: 0,
+            regexes: HashMap::new(),
+            scope_guards: vec![],
+            iterations: vec![],
+            sequences: vec![],
+            frame_base: 0,
         }
     }
 }
@@ -64,10 +160,115 @@ impl Memory {
         self.stack.push(val);
     }
 
+    /// pushes copies of the elements of the list at the stack index onto the stack, and returns
+    /// how many there were. The list stays the owner of the elements
+    pub unsafe fn spread_vec(&mut self, idx: usize) -> Option<usize> {
+        let elems = iteration::list_elems(&self.stack[idx])?;
+        let len = elems.len();
+        self.stack.extend(elems);
+        Some(len)
+    }
+
     pub fn store(&mut self, storable: Storable) {
+        let data = self.alloc(storable);
+        self.stack.push(data);
+    }
+
+    /// like store, but the data is returned instead of being pushed onto the stack
+    pub fn alloc(&mut self, storable: Storable) -> RuntimeData {
         match storable {
-            Storable::OnHeap(x) => self.push_heap(x),
-            Storable::OnStack(x) => self.push_stack(x),
+            Storable::OnHeap(x) => self.heap_ref(x),
+            Storable::OnStack(x) => x,
+            Storable::StrVecOnHeap(strs) => {
+                let elems = strs
+                    .into_iter()
+                    .map(|s| self.heap_ref(Value::Str(s)))
+                    .collect();
+                self.heap_ref(Value::Vec(elems))
+            }
+            Storable::VecOnHeap(elems) => {
+                let elems = elems.into_iter().map(|e| self.alloc(e)).collect();
+                self.heap_ref(Value::Vec(elems))
+            }
+            Storable::SetOnHeap(elems) => {
+                let elems = elems.into_iter().map(|e| self.alloc(e)).collect();
+                self.heap_ref(Value::Set(elems))
+            }
+            Storable::DictOnHeap(entries) => {
+                let entries = entries
+                    .into_iter()
+                    .map(|(k, v)| (self.alloc(k), self.alloc(v)))
+                    .collect();
+                self.heap_ref(Value::Dict(entries))
+            }
+            Storable::JsonOnHeap(inner) => {
+                let inner = self.alloc(*inner);
+                self.heap_ref(Value::Json(inner))
+            }
+            Storable::SeqOnHeap(seq) => {
+                self.sequences.push(Some(seq));
+                self.heap_ref(Value::Seq(self.sequences.len() - 1))
+            }
+            Storable::OptOnHeap(inner) => {
+                let inner = inner.map(|inner| self.alloc(*inner));
+                self.heap_ref(Value::Opt(inner))
+            }
+        }
+    }
+
+    /// puts a value on the heap, and returns a ref to it, which owns it
+    fn heap_ref(&mut self, val: RuntimeValue) -> RuntimeData {
+        let heap_idx = self.heap.push(val);
+        let ptr: *const RuntimeValue = unsafe { self.heap.get(heap_idx) };
+        Data::Ref(RuntimeRef::HeapRef(ptr))
+    }
+
+    /// compiles the regex, or takes it from the cache
+    pub fn regex(&mut self, pattern: &str) -> Result<&Regex> {
+        if !self.regexes.contains_key(pattern) {
+            let regex = Regex::new(pattern).map_err(|e| rt_err!("Invalid regex: {}", e))?;
+            self.regexes.insert(pattern.into(), regex);
+        }
+        Ok(&self.regexes[pattern])
+    }
+
+    /// the state of a sequence, fails if it was consumed by another sequence
+    pub fn sequence(&mut self, idx: usize) -> Result<&mut Sequence> {
+        self.sequences[idx]
+            .as_mut()
+            .ok_or_else(|| rt_err!("The sequence was already consumed"))
+    }
+
+    /// produces the next element of the sequence. Its state is taken out meanwhile, because the
+    /// fragment of a lazy sequence runs with the memory
+    pub unsafe fn next_elem(&mut self, idx: usize) -> Result<Option<Storable>> {
+        let mut seq = self.take_sequence(idx)?;
+        let elem = seq.next_elem(self);
+        self.sequences[idx] = Some(seq);
+        elem
+    }
+
+    /// removes the state of a sequence, so that another sequence can consume it
+    pub fn take_sequence(&mut self, idx: usize) -> Result<Sequence> {
+        self.sequences[idx]
+            .take()
+            .ok_or_else(|| rt_err!("The sequence was already consumed"))
+    }
+
+    /// undoes the changes of the innermost scoped built in
+    pub fn leave_scope(&mut self) -> Result<()> {
+        self.scope_guards
+            .pop()
+            .ok_or_else(|| rt_err!("Left a scope that was never entered"))?
+            .undo()
+    }
+
+    /// ends the innermost scoped built in, which must be measure, and returns how long its body
+    /// took in milliseconds
+    pub fn leave_measure(&mut self) -> Result<i64> {
+        match self.scope_guards.pop() {
+            Some(ScopeGuard::Measure(start)) => Ok(start.elapsed().as_millis() as i64),
+            _ => Err(rt_err!("Left a measured scope that was never entered")),
         }
     }
 
@@ -84,7 +285,122 @@ impl Memory {
         let Data::Ref(RuntimeRef::HeapRef(addr)) = r else {
             panic!("Pop_free found: {:#?}", r);
         };
-        unsafe { self.heap.free(addr) };
+        unsafe { self.free_value(addr) };
+    }
+
+    /// frees the value the data refers to, if it is on the heap
+    pub fn free(&mut self, d: RuntimeData) {
+        if let Data::Ref(RuntimeRef::HeapRef(addr)) = d {
+            unsafe { self.free_value(addr) };
+        }
+    }
+
+    /// frees a value on the heap. Containers own the heap values their elements refer to,
+    /// so those are freed too
+    unsafe fn free_value(&mut self, addr: *const RuntimeValue) {
+        let elems: Vec<RuntimeData> = match &*addr {
+            Value::Vec(v) => v.iter().cloned().collect(),
+            Value::Set(s) => s.iter().cloned().collect(),
+            Value::Dict(d) => d.iter().flat_map(|(k, v)| [k.clone(), v.clone()]).collect(),
+            Value::Json(d) => vec![d.clone()],
+            Value::Opt(d) => d.iter().cloned().collect(),
+            // closes the file, or stops the program
+            Value::Seq(idx) => self.sequences[*idx]
+                .take()
+                .map(Sequence::into_captured)
+                .unwrap_or_default(),
+            Value::Str(_) | Value::Keyword(_) | Value::Regex(_) => vec![],
+        };
+        for elem in elems {
+            if let Data::Ref(RuntimeRef::HeapRef(elem_addr)) = elem {
+                self.free_value(elem_addr);
+            }
+        }
+        self.heap.free(addr);
+    }
+}
+
+impl Drop for Memory {
+    fn drop(&mut self) {
+        for bg in &mut self.children {
+            if let Ok(None) = bg.child.try_wait() {
+                // the process might have finished in the meantime, so this may fail
+                let _ = bg.child.kill();
+            }
+            let _ = bg.child.wait();
+        }
+        // the script failed inside of scoped built ins. The children are gone, so nothing uses
+        // what is cleaned up anymore
+        while let Some(guard) = self.scope_guards.pop() {
+            let _ = guard.undo();
+        }
+    }
+}
+
+impl BgProcess {
+    /// collects the output of the streams that were piped, the others stay empty
+    pub fn new(mut child: Child) -> Self {
+        let stdout = Captured::start(child.stdout.take());
+        let stderr = Captured::start(child.stderr.take());
+        BgProcess {
+            child,
+            stdout,
+            stderr,
+        }
+    }
+
+    /// stdout and stderr. Blocks until the process closed them, which usually happens when it
+    /// ends. Output that isn't valid UTF-8 is converted lossily
+    pub fn output(&mut self) -> (String, String) {
+        (self.stdout.finish().into(), self.stderr.finish().into())
+    }
+}
+
+impl Captured {
+    fn start(stream: Option<impl Read + Send + 'static>) -> Self {
+        let reader = stream.map(|mut stream| {
+            thread::spawn(move || {
+                let mut buf = vec![];
+                // a read error ends the output, what was read until then is kept
+                let _ = stream.read_to_end(&mut buf);
+                buf
+            })
+        });
+        Captured {
+            reader,
+            output: String::new(),
+        }
+    }
+
+    fn finish(&mut self) -> &str {
+        if let Some(reader) = self.reader.take() {
+            let buf = reader.join().unwrap_or_default();
+            self.output = String::from_utf8_lossy(&buf).into_owned();
+        }
+        &self.output
+    }
+}
+
+impl ScopeGuard {
+    fn undo(self) -> Result<()> {
+        match self {
+            ScopeGuard::Env(name, Some(value)) => std::env::set_var(name, value),
+            ScopeGuard::Env(name, None) => std::env::remove_var(name),
+            ScopeGuard::Cwd(dir) => std::env::set_current_dir(&dir)
+                .map_err(|e| rt_err!("Changing back to {}: {}", dir.display(), e))?,
+            // the body may have deleted it already
+            ScopeGuard::TmpPath(path) if path.is_dir() => std::fs::remove_dir_all(&path)
+                .map_err(|e| rt_err!("Deleting {}: {}", path.display(), e))?,
+            ScopeGuard::TmpPath(path) if path.exists() => std::fs::remove_file(&path)
+                .map_err(|e| rt_err!("Deleting {}: {}", path.display(), e))?,
+            ScopeGuard::TmpPath(_) | ScopeGuard::Measure(_) | ScopeGuard::Nothing => {}
+            ScopeGuard::Spinner(stop, thread) => {
+                stop.store(true, Ordering::Relaxed);
+                let _ = thread.join();
+                drop(crate::vm::built_ins::clear_spinner());
+            }
+        }
+        Ok(())
     }
 }
 
@@ -97,8 +413,37 @@ where
     }
 }
 
+impl<T: Into<Storable>> From<Option<T>> for Storable {
+    fn from(value: Option<T>) -> Self {
+        Storable::OptOnHeap(value.map(|v| Box::new(v.into())))
+    }
+}
+
 impl From<String> for Storable {
     fn from(value: String) -> Self {
         Storable::OnHeap(Value::Str(value))
     }
 }
+
+impl From<Vec<String>> for Storable {
+    fn from(value: Vec<String>) -> Self {
+        Storable::StrVecOnHeap(value)
+    }
+}
+
+impl From<Vec<Vec<String>>> for Storable {
+    fn from(value: Vec<Vec<String>>) -> Self {
+        Storable::VecOnHeap(value.into_iter().map(Storable::from).collect())
+    }
+}
+
+impl From<Vec<(String, String)>> for Storable {
+    fn from(value: Vec<(String, String)>) -> Self {
+        Storable::DictOnHeap(
+            value
+                .into_iter()
+                .map(|(k, v)| (k.into(), v.into()))
+                .collect(),
+        )
+    }
+}
@@ -4,11 +4,20 @@ use crate::core::*;
 use std::result::Result as StdResult;
 use thiserror::Error;
 
+pub mod args;
 pub mod built_ins;
+pub mod globals;
+pub mod iteration;
+pub mod json;
 pub mod memory;
+pub mod sequence;
+pub mod signals;
 
 pub use built_ins::*;
+pub use globals::*;
+pub use iteration::{IterKind, Iteration};
 pub use memory::*;
+pub use sequence::Sequence;
 
 #[derive(Error, Debug)]
 pub enum Error {
@@ -42,6 +51,10 @@ pub enum Error {
 
     #[error("Unknown Builtin: {0}")]
     UnknownBuiltIn(String),
+
+    /// the script received the signal, see [signals]
+    #[error("Interrupted by signal {0}")]
+    Interrupted(i32),
 }
 
 // fn type_error<T: ?Sized>(d: RuntimeData) -> Error {
@@ -108,12 +121,748 @@ pub unsafe fn exec_strcat(pc: *const u8, mem: &mut Memory) -> ExecResult {
     ok_pc!(pc.offset(isize_of!(STRCAT)))
 }
 
+pub unsafe fn exec_spawn(pc: *const u8, mem: &mut Memory) -> ExecResult {
+    built_ins::wrapper_mem_1_var_ret(built_ins::impls::spawn, "spawn", mem)?;
+    ok_pc!(pc.offset(isize_of!(SPAWN)))
+}
+
+pub unsafe fn exec_isrunning(pc: *const u8, mem: &mut Memory) -> ExecResult {
+    built_ins::wrapper_mem_1_ret(built_ins::impls::is_running, "isRunning", mem)?;
+    ok_pc!(pc.offset(isize_of!(ISRUNNING)))
+}
+
+pub unsafe fn exec_wait(pc: *const u8, mem: &mut Memory) -> ExecResult {
+    built_ins::wrapper_mem_1_ret(built_ins::impls::wait, "wait", mem)?;
+    ok_pc!(pc.offset(isize_of!(WAIT)))
+}
+
+pub unsafe fn exec_captured(pc: *const u8, mem: &mut Memory) -> ExecResult {
+    built_ins::wrapper_mem_1_ret(built_ins::impls::captured, "captured", mem)?;
+    ok_pc!(pc.offset(isize_of!(CAPTURED)))
+}
+
+pub unsafe fn exec_kill(pc: *const u8, mem: &mut Memory) -> ExecResult {
+    built_ins::wrapper_mem_2_ret(built_ins::impls::kill, "kill", mem)?;
+    ok_pc!(pc.offset(isize_of!(KILL)))
+}
+
+pub unsafe fn exec_glob(pc: *const u8, mem: &mut Memory) -> ExecResult {
+    built_ins::wrapper_1_ret(built_ins::impls::glob, "glob", mem)?;
+    ok_pc!(pc.offset(isize_of!(GLOB)))
+}
+
+pub unsafe fn exec_globstrict(pc: *const u8, mem: &mut Memory) -> ExecResult {
+    built_ins::wrapper_1_ret(built_ins::impls::glob_strict, "globStrict", mem)?;
+    ok_pc!(pc.offset(isize_of!(GLOBSTRICT)))
+}
+
+pub unsafe fn exec_escapeglob(pc: *const u8, mem: &mut Memory) -> ExecResult {
+    built_ins::wrapper_1_ret(built_ins::impls::escape_glob, "escapeGlob", mem)?;
+    ok_pc!(pc.offset(isize_of!(ESCAPEGLOB)))
+}
+
+pub unsafe fn exec_globmatch(pc: *const u8, mem: &mut Memory) -> ExecResult {
+    built_ins::wrapper_2_ret(built_ins::impls::glob_match, "globMatch", mem)?;
+    ok_pc!(pc.offset(isize_of!(GLOBMATCH)))
+}
+
+pub unsafe fn exec_read(pc: *const u8, mem: &mut Memory) -> ExecResult {
+    built_ins::wrapper_1_ret(built_ins::impls::read, "read", mem)?;
+    ok_pc!(pc.offset(isize_of!(READ)))
+}
+
+pub unsafe fn exec_write(pc: *const u8, mem: &mut Memory) -> ExecResult {
+    built_ins::wrapper_2_ret(built_ins::impls::write, "write", mem)?;
+    ok_pc!(pc.offset(isize_of!(WRITE)))
+}
+
+pub unsafe fn exec_exists(pc: *const u8, mem: &mut Memory) -> ExecResult {
+    built_ins::wrapper_1_ret(built_ins::impls::exists, "exists", mem)?;
+    ok_pc!(pc.offset(isize_of!(EXISTS)))
+}
+
+pub unsafe fn exec_isdir(pc: *const u8, mem: &mut Memory) -> ExecResult {
+    built_ins::wrapper_1_ret(built_ins::impls::is_dir, "isDir", mem)?;
+    ok_pc!(pc.offset(isize_of!(ISDIR)))
+}
+
+pub unsafe fn exec_isfile(pc: *const u8, mem: &mut Memory) -> ExecResult {
+    built_ins::wrapper_1_ret(built_ins::impls::is_file, "isFile", mem)?;
+    ok_pc!(pc.offset(isize_of!(ISFILE)))
+}
+
+pub unsafe fn exec_isrelative(pc: *const u8, mem: &mut Memory) -> ExecResult {
+    built_ins::wrapper_1_ret(built_ins::impls::is_relative, "isRelative", mem)?;
+    ok_pc!(pc.offset(isize_of!(ISRELATIVE)))
+}
+
+pub unsafe fn exec_name(pc: *const u8, mem: &mut Memory) -> ExecResult {
+    built_ins::wrapper_1_ret(built_ins::impls::name, "name", mem)?;
+    ok_pc!(pc.offset(isize_of!(NAME)))
+}
+
+pub unsafe fn exec_stem(pc: *const u8, mem: &mut Memory) -> ExecResult {
+    built_ins::wrapper_1_ret(built_ins::impls::stem, "stem", mem)?;
+    ok_pc!(pc.offset(isize_of!(STEM)))
+}
+
+pub unsafe fn exec_extension(pc: *const u8, mem: &mut Memory) -> ExecResult {
+    built_ins::wrapper_1_ret(built_ins::impls::extension, "extension", mem)?;
+    ok_pc!(pc.offset(isize_of!(EXTENSION)))
+}
+
+pub unsafe fn exec_parent(pc: *const u8, mem: &mut Memory) -> ExecResult {
+    built_ins::wrapper_1_ret(built_ins::impls::parent, "parent", mem)?;
+    ok_pc!(pc.offset(isize_of!(PARENT)))
+}
+
+pub unsafe fn exec_joinpath(pc: *const u8, mem: &mut Memory) -> ExecResult {
+    built_ins::wrapper_2_ret(built_ins::impls::join_path, "joinPath", mem)?;
+    ok_pc!(pc.offset(isize_of!(JOINPATH)))
+}
+
+pub unsafe fn exec_canonicalize(pc: *const u8, mem: &mut Memory) -> ExecResult {
+    built_ins::wrapper_1_ret(built_ins::impls::canonicalize, "canonicalize", mem)?;
+    ok_pc!(pc.offset(isize_of!(CANONICALIZE)))
+}
+
+pub unsafe fn exec_expandhome(pc: *const u8, mem: &mut Memory) -> ExecResult {
+    built_ins::wrapper_1_ret(built_ins::impls::expand_home, "expandHome", mem)?;
+    ok_pc!(pc.offset(isize_of!(EXPANDHOME)))
+}
+
+pub unsafe fn exec_readfile(pc: *const u8, mem: &mut Memory) -> ExecResult {
+    built_ins::wrapper_1_ret(built_ins::impls::read_file, "readFile", mem)?;
+    ok_pc!(pc.offset(isize_of!(READFILE)))
+}
+
+pub unsafe fn exec_readlines(pc: *const u8, mem: &mut Memory) -> ExecResult {
+    built_ins::wrapper_1_ret(built_ins::impls::read_lines, "readLines", mem)?;
+    ok_pc!(pc.offset(isize_of!(READLINES)))
+}
+
+pub unsafe fn exec_writefile(pc: *const u8, mem: &mut Memory) -> ExecResult {
+    built_ins::wrapper_2_ret(built_ins::impls::write_file, "writeFile", mem)?;
+    ok_pc!(pc.offset(isize_of!(WRITEFILE)))
+}
+
+pub unsafe fn exec_appendfile(pc: *const u8, mem: &mut Memory) -> ExecResult {
+    built_ins::wrapper_2_ret(built_ins::impls::append_file, "appendFile", mem)?;
+    ok_pc!(pc.offset(isize_of!(APPENDFILE)))
+}
+
+pub unsafe fn exec_delete(pc: *const u8, mem: &mut Memory) -> ExecResult {
+    built_ins::wrapper_1_ret(built_ins::impls::delete, "delete", mem)?;
+    ok_pc!(pc.offset(isize_of!(DELETE)))
+}
+
+pub unsafe fn exec_copy(pc: *const u8, mem: &mut Memory) -> ExecResult {
+    built_ins::wrapper_2_ret(built_ins::impls::copy, "copy", mem)?;
+    ok_pc!(pc.offset(isize_of!(COPY)))
+}
+
+pub unsafe fn exec_move(pc: *const u8, mem: &mut Memory) -> ExecResult {
+    built_ins::wrapper_2_ret(built_ins::impls::move_path, "move", mem)?;
+    ok_pc!(pc.offset(isize_of!(MOVE)))
+}
+
+pub unsafe fn exec_makedir(pc: *const u8, mem: &mut Memory) -> ExecResult {
+    built_ins::wrapper_1_ret(built_ins::impls::make_dir, "makeDir", mem)?;
+    ok_pc!(pc.offset(isize_of!(MAKEDIR)))
+}
+
+pub unsafe fn exec_split(pc: *const u8, mem: &mut Memory) -> ExecResult {
+    built_ins::wrapper_2_ret(built_ins::impls::split, "split", mem)?;
+    ok_pc!(pc.offset(isize_of!(SPLIT)))
+}
+
+pub unsafe fn exec_lines(pc: *const u8, mem: &mut Memory) -> ExecResult {
+    built_ins::wrapper_1_ret(built_ins::impls::lines, "lines", mem)?;
+    ok_pc!(pc.offset(isize_of!(LINES)))
+}
+
+pub unsafe fn exec_join(pc: *const u8, mem: &mut Memory) -> ExecResult {
+    built_ins::wrapper_2_ret(built_ins::impls::join, "join", mem)?;
+    ok_pc!(pc.offset(isize_of!(JOIN)))
+}
+
+pub unsafe fn exec_trim(pc: *const u8, mem: &mut Memory) -> ExecResult {
+    built_ins::wrapper_1_ret(built_ins::impls::trim, "trim", mem)?;
+    ok_pc!(pc.offset(isize_of!(TRIM)))
+}
+
+pub unsafe fn exec_trimstart(pc: *const u8, mem: &mut Memory) -> ExecResult {
+    built_ins::wrapper_1_ret(built_ins::impls::trim_start, "trimStart", mem)?;
+    ok_pc!(pc.offset(isize_of!(TRIMSTART)))
+}
+
+pub unsafe fn exec_trimend(pc: *const u8, mem: &mut Memory) -> ExecResult {
+    built_ins::wrapper_1_ret(built_ins::impls::trim_end, "trimEnd", mem)?;
+    ok_pc!(pc.offset(isize_of!(TRIMEND)))
+}
+
+pub unsafe fn exec_replace(pc: *const u8, mem: &mut Memory) -> ExecResult {
+    built_ins::wrapper_3_ret(built_ins::impls::replace, "replace", mem)?;
+    ok_pc!(pc.offset(isize_of!(REPLACE)))
+}
+
+pub unsafe fn exec_startswith(pc: *const u8, mem: &mut Memory) -> ExecResult {
+    built_ins::wrapper_2_ret(built_ins::impls::starts_with, "startsWith", mem)?;
+    ok_pc!(pc.offset(isize_of!(STARTSWITH)))
+}
+
+pub unsafe fn exec_endswith(pc: *const u8, mem: &mut Memory) -> ExecResult {
+    built_ins::wrapper_2_ret(built_ins::impls::ends_with, "endsWith", mem)?;
+    ok_pc!(pc.offset(isize_of!(ENDSWITH)))
+}
+
+pub unsafe fn exec_contains(pc: *const u8, mem: &mut Memory) -> ExecResult {
+    built_ins::wrapper_2_ret(built_ins::impls::contains, "contains", mem)?;
+    ok_pc!(pc.offset(isize_of!(CONTAINS)))
+}
+
+pub unsafe fn exec_upper(pc: *const u8, mem: &mut Memory) -> ExecResult {
+    built_ins::wrapper_1_ret(built_ins::impls::upper, "upper", mem)?;
+    ok_pc!(pc.offset(isize_of!(UPPER)))
+}
+
+pub unsafe fn exec_lower(pc: *const u8, mem: &mut Memory) -> ExecResult {
+    built_ins::wrapper_1_ret(built_ins::impls::lower, "lower", mem)?;
+    ok_pc!(pc.offset(isize_of!(LOWER)))
+}
+
+pub unsafe fn exec_len(pc: *const u8, mem: &mut Memory) -> ExecResult {
+    built_ins::wrapper_1_ret(built_ins::impls::len, "len", mem)?;
+    ok_pc!(pc.offset(isize_of!(LEN)))
+}
+
+pub unsafe fn exec_bytelen(pc: *const u8, mem: &mut Memory) -> ExecResult {
+    built_ins::wrapper_1_ret(built_ins::impls::byte_len, "byteLen", mem)?;
+    ok_pc!(pc.offset(isize_of!(BYTELEN)))
+}
+
+pub unsafe fn exec_slice(pc: *const u8, mem: &mut Memory) -> ExecResult {
+    built_ins::wrapper_3_ret(built_ins::impls::slice, "slice", mem)?;
+    ok_pc!(pc.offset(isize_of!(SLICE)))
+}
+
+pub unsafe fn exec_byteslice(pc: *const u8, mem: &mut Memory) -> ExecResult {
+    built_ins::wrapper_3_ret(built_ins::impls::byte_slice, "byteSlice", mem)?;
+    ok_pc!(pc.offset(isize_of!(BYTESLICE)))
+}
+
+pub unsafe fn exec_regex(pc: *const u8, mem: &mut Memory) -> ExecResult {
+    built_ins::wrapper_mem_1_ret(built_ins::impls::regex, "regex", mem)?;
+    ok_pc!(pc.offset(isize_of!(REGEX)))
+}
+
+pub unsafe fn exec_matches(pc: *const u8, mem: &mut Memory) -> ExecResult {
+    built_ins::wrapper_mem_2_ret(built_ins::impls::matches, "matches", mem)?;
+    ok_pc!(pc.offset(isize_of!(MATCHES)))
+}
+
+pub unsafe fn exec_find(pc: *const u8, mem: &mut Memory) -> ExecResult {
+    built_ins::wrapper_mem_2_ret(built_ins::impls::find, "find", mem)?;
+    ok_pc!(pc.offset(isize_of!(FIND)))
+}
+
+pub unsafe fn exec_findall(pc: *const u8, mem: &mut Memory) -> ExecResult {
+    built_ins::wrapper_mem_2_ret(built_ins::impls::find_all, "findAll", mem)?;
+    ok_pc!(pc.offset(isize_of!(FINDALL)))
+}
+
+pub unsafe fn exec_captures(pc: *const u8, mem: &mut Memory) -> ExecResult {
+    built_ins::wrapper_mem_2_ret(built_ins::impls::captures, "captures", mem)?;
+    ok_pc!(pc.offset(isize_of!(CAPTURES)))
+}
+
+pub unsafe fn exec_replaceall(pc: *const u8, mem: &mut Memory) -> ExecResult {
+    built_ins::wrapper_mem_3_ret(built_ins::impls::replace_all, "replaceAll", mem)?;
+    ok_pc!(pc.offset(isize_of!(REPLACEALL)))
+}
+
+pub unsafe fn exec_fromjson(pc: *const u8, mem: &mut Memory) -> ExecResult {
+    built_ins::wrapper_1_ret(built_ins::impls::from_json, "fromJson", mem)?;
+    ok_pc!(pc.offset(isize_of!(FROMJSON)))
+}
+
+pub unsafe fn exec_tojson(pc: *const u8, mem: &mut Memory) -> ExecResult {
+    built_ins::wrapper_1_ret(built_ins::impls::to_json, "toJson", mem)?;
+    ok_pc!(pc.offset(isize_of!(TOJSON)))
+}
+
+pub unsafe fn exec_tojsonpretty(pc: *const u8, mem: &mut Memory) -> ExecResult {
+    built_ins::wrapper_1_ret(built_ins::impls::to_json_pretty, "toJsonPretty", mem)?;
+    ok_pc!(pc.offset(isize_of!(TOJSONPRETTY)))
+}
+
+pub unsafe fn exec_jsontype(pc: *const u8, mem: &mut Memory) -> ExecResult {
+    built_ins::wrapper_1_ret(built_ins::impls::json_type, "jsonType", mem)?;
+    ok_pc!(pc.offset(isize_of!(JSONTYPE)))
+}
+
+pub unsafe fn exec_jsonget(pc: *const u8, mem: &mut Memory) -> ExecResult {
+    built_ins::wrapper_2_ret(built_ins::impls::json_get, "jsonGet", mem)?;
+    ok_pc!(pc.offset(isize_of!(JSONGET)))
+}
+
+pub unsafe fn exec_jsonat(pc: *const u8, mem: &mut Memory) -> ExecResult {
+    built_ins::wrapper_2_ret(built_ins::impls::json_at, "jsonAt", mem)?;
+    ok_pc!(pc.offset(isize_of!(JSONAT)))
+}
+
+pub unsafe fn exec_jsonkeys(pc: *const u8, mem: &mut Memory) -> ExecResult {
+    built_ins::wrapper_1_ret(built_ins::impls::json_keys, "jsonKeys", mem)?;
+    ok_pc!(pc.offset(isize_of!(JSONKEYS)))
+}
+
+pub unsafe fn exec_jsonlist(pc: *const u8, mem: &mut Memory) -> ExecResult {
+    built_ins::wrapper_1_ret(built_ins::impls::json_list, "jsonList", mem)?;
+    ok_pc!(pc.offset(isize_of!(JSONLIST)))
+}
+
+pub unsafe fn exec_jsonstr(pc: *const u8, mem: &mut Memory) -> ExecResult {
+    built_ins::wrapper_1_ret(built_ins::impls::json_str, "jsonStr", mem)?;
+    ok_pc!(pc.offset(isize_of!(JSONSTR)))
+}
+
+pub unsafe fn exec_jsonint(pc: *const u8, mem: &mut Memory) -> ExecResult {
+    built_ins::wrapper_1_ret(built_ins::impls::json_int, "jsonInt", mem)?;
+    ok_pc!(pc.offset(isize_of!(JSONINT)))
+}
+
+pub unsafe fn exec_jsonfloat(pc: *const u8, mem: &mut Memory) -> ExecResult {
+    built_ins::wrapper_1_ret(built_ins::impls::json_float, "jsonFloat", mem)?;
+    ok_pc!(pc.offset(isize_of!(JSONFLOAT)))
+}
+
+pub unsafe fn exec_jsonbool(pc: *const u8, mem: &mut Memory) -> ExecResult {
+    built_ins::wrapper_1_ret(built_ins::impls::json_bool, "jsonBool", mem)?;
+    ok_pc!(pc.offset(isize_of!(JSONBOOL)))
+}
+
+pub unsafe fn exec_getenv(pc: *const u8, mem: &mut Memory) -> ExecResult {
+    built_ins::wrapper_1_ret(built_ins::impls::get_env, "getEnv", mem)?;
+    ok_pc!(pc.offset(isize_of!(GETENV)))
+}
+
+pub unsafe fn exec_getenvor(pc: *const u8, mem: &mut Memory) -> ExecResult {
+    built_ins::wrapper_2_ret(built_ins::impls::get_env_or, "getEnvOr", mem)?;
+    ok_pc!(pc.offset(isize_of!(GETENVOR)))
+}
+
+pub unsafe fn exec_hasenv(pc: *const u8, mem: &mut Memory) -> ExecResult {
+    built_ins::wrapper_1_ret(built_ins::impls::has_env, "hasEnv", mem)?;
+    ok_pc!(pc.offset(isize_of!(HASENV)))
+}
+
+pub unsafe fn exec_setenv(pc: *const u8, mem: &mut Memory) -> ExecResult {
+    built_ins::wrapper_2_ret(built_ins::impls::set_env, "setEnv", mem)?;
+    ok_pc!(pc.offset(isize_of!(SETENV)))
+}
+
+pub unsafe fn exec_unsetenv(pc: *const u8, mem: &mut Memory) -> ExecResult {
+    built_ins::wrapper_1_ret(built_ins::impls::unset_env, "unsetEnv", mem)?;
+    ok_pc!(pc.offset(isize_of!(UNSETENV)))
+}
+
+pub unsafe fn exec_withenv(pc: *const u8, mem: &mut Memory) -> ExecResult {
+    built_ins::wrapper_mem_2_ret(built_ins::impls::with_env, "withEnv", mem)?;
+    ok_pc!(pc.offset(isize_of!(WITHENV)))
+}
+
+pub unsafe fn exec_withcwd(pc: *const u8, mem: &mut Memory) -> ExecResult {
+    built_ins::wrapper_mem_1_ret(built_ins::impls::with_cwd, "withCwd", mem)?;
+    ok_pc!(pc.offset(isize_of!(WITHCWD)))
+}
+
+pub unsafe fn exec_withtmppath(pc: *const u8, mem: &mut Memory) -> ExecResult {
+    built_ins::wrapper_mem_0_ret(built_ins::impls::with_tmp_path, "withTmpPath", mem)?;
+    ok_pc!(pc.offset(isize_of!(WITHTMPPATH)))
+}
+
+pub unsafe fn exec_withtmpdir(pc: *const u8, mem: &mut Memory) -> ExecResult {
+    built_ins::wrapper_mem_0_ret(built_ins::impls::with_tmp_dir, "withTmpDir", mem)?;
+    ok_pc!(pc.offset(isize_of!(WITHTMPDIR)))
+}
+
+pub unsafe fn exec_print(pc: *const u8, mem: &mut Memory) -> ExecResult {
+    built_ins::wrapper_0_var_ret(built_ins::impls::print, "print", mem)?;
+    ok_pc!(pc.offset(isize_of!(PRINT)))
+}
+
+pub unsafe fn exec_println(pc: *const u8, mem: &mut Memory) -> ExecResult {
+    built_ins::wrapper_0_var_ret(built_ins::impls::println, "println", mem)?;
+    ok_pc!(pc.offset(isize_of!(PRINTLN)))
+}
+
+pub unsafe fn exec_eprint(pc: *const u8, mem: &mut Memory) -> ExecResult {
+    built_ins::wrapper_0_var_ret(built_ins::impls::eprint, "eprint", mem)?;
+    ok_pc!(pc.offset(isize_of!(EPRINT)))
+}
+
+pub unsafe fn exec_eprintln(pc: *const u8, mem: &mut Memory) -> ExecResult {
+    built_ins::wrapper_0_var_ret(built_ins::impls::eprintln, "eprintln", mem)?;
+    ok_pc!(pc.offset(isize_of!(EPRINTLN)))
+}
+
+pub unsafe fn exec_readline(pc: *const u8, mem: &mut Memory) -> ExecResult {
+    built_ins::wrapper_0_ret(built_ins::impls::read_line, "readLine", mem)?;
+    ok_pc!(pc.offset(isize_of!(READLINE)))
+}
+
+pub unsafe fn exec_readall(pc: *const u8, mem: &mut Memory) -> ExecResult {
+    built_ins::wrapper_0_ret(built_ins::impls::read_all, "readAll", mem)?;
+    ok_pc!(pc.offset(isize_of!(READALL)))
+}
+
+pub unsafe fn exec_stdinlines(pc: *const u8, mem: &mut Memory) -> ExecResult {
+    built_ins::wrapper_0_ret(built_ins::impls::stdin_lines, "stdinLines", mem)?;
+    ok_pc!(pc.offset(isize_of!(STDINLINES)))
+}
+
+pub unsafe fn exec_prompt(pc: *const u8, mem: &mut Memory) -> ExecResult {
+    built_ins::wrapper_1_ret(built_ins::impls::prompt, "prompt", mem)?;
+    ok_pc!(pc.offset(isize_of!(PROMPT)))
+}
+
+pub unsafe fn exec_confirm(pc: *const u8, mem: &mut Memory) -> ExecResult {
+    built_ins::wrapper_2_ret(built_ins::impls::confirm, "confirm", mem)?;
+    ok_pc!(pc.offset(isize_of!(CONFIRM)))
+}
+
+pub unsafe fn exec_password(pc: *const u8, mem: &mut Memory) -> ExecResult {
+    built_ins::wrapper_1_ret(built_ins::impls::password, "password", mem)?;
+    ok_pc!(pc.offset(isize_of!(PASSWORD)))
+}
+
+pub unsafe fn exec_color(pc: *const u8, mem: &mut Memory) -> ExecResult {
+    built_ins::wrapper_2_ret(built_ins::impls::color, "color", mem)?;
+    ok_pc!(pc.offset(isize_of!(COLOR)))
+}
+
+pub unsafe fn exec_bgcolor(pc: *const u8, mem: &mut Memory) -> ExecResult {
+    built_ins::wrapper_2_ret(built_ins::impls::bg_color, "bgColor", mem)?;
+    ok_pc!(pc.offset(isize_of!(BGCOLOR)))
+}
+
+pub unsafe fn exec_bold(pc: *const u8, mem: &mut Memory) -> ExecResult {
+    built_ins::wrapper_1_ret(built_ins::impls::bold, "bold", mem)?;
+    ok_pc!(pc.offset(isize_of!(BOLD)))
+}
+
+pub unsafe fn exec_dim(pc: *const u8, mem: &mut Memory) -> ExecResult {
+    built_ins::wrapper_1_ret(built_ins::impls::dim, "dim", mem)?;
+    ok_pc!(pc.offset(isize_of!(DIM)))
+}
+
+pub unsafe fn exec_italic(pc: *const u8, mem: &mut Memory) -> ExecResult {
+    built_ins::wrapper_1_ret(built_ins::impls::italic, "italic", mem)?;
+    ok_pc!(pc.offset(isize_of!(ITALIC)))
+}
+
+pub unsafe fn exec_underline(pc: *const u8, mem: &mut Memory) -> ExecResult {
+    built_ins::wrapper_1_ret(built_ins::impls::underline, "underline", mem)?;
+    ok_pc!(pc.offset(isize_of!(UNDERLINE)))
+}
+
+pub unsafe fn exec_isterminal(pc: *const u8, mem: &mut Memory) -> ExecResult {
+    built_ins::wrapper_1_ret(built_ins::impls::is_terminal, "isTerminal", mem)?;
+    ok_pc!(pc.offset(isize_of!(ISTERMINAL)))
+}
+
+pub unsafe fn exec_termwidth(pc: *const u8, mem: &mut Memory) -> ExecResult {
+    built_ins::wrapper_0_ret(built_ins::impls::term_width, "termWidth", mem)?;
+    ok_pc!(pc.offset(isize_of!(TERMWIDTH)))
+}
+
+pub unsafe fn exec_progress(pc: *const u8, mem: &mut Memory) -> ExecResult {
+    built_ins::wrapper_3_ret(built_ins::impls::progress, "progress", mem)?;
+    ok_pc!(pc.offset(isize_of!(PROGRESS)))
+}
+
+pub unsafe fn exec_withspinner(pc: *const u8, mem: &mut Memory) -> ExecResult {
+    built_ins::wrapper_mem_1_ret(built_ins::impls::with_spinner, "withSpinner", mem)?;
+    ok_pc!(pc.offset(isize_of!(WITHSPINNER)))
+}
+
+pub unsafe fn exec_now(pc: *const u8, mem: &mut Memory) -> ExecResult {
+    built_ins::wrapper_0_ret(built_ins::impls::now, "now", mem)?;
+    ok_pc!(pc.offset(isize_of!(NOW)))
+}
+
+pub unsafe fn exec_since(pc: *const u8, mem: &mut Memory) -> ExecResult {
+    built_ins::wrapper_1_ret(built_ins::impls::since, "since", mem)?;
+    ok_pc!(pc.offset(isize_of!(SINCE)))
+}
+
+pub unsafe fn exec_addtime(pc: *const u8, mem: &mut Memory) -> ExecResult {
+    built_ins::wrapper_2_ret(built_ins::impls::add_time, "addTime", mem)?;
+    ok_pc!(pc.offset(isize_of!(ADDTIME)))
+}
+
+pub unsafe fn exec_timebetween(pc: *const u8, mem: &mut Memory) -> ExecResult {
+    built_ins::wrapper_2_ret(built_ins::impls::time_between, "timeBetween", mem)?;
+    ok_pc!(pc.offset(isize_of!(TIMEBETWEEN)))
+}
+
+pub unsafe fn exec_formattime(pc: *const u8, mem: &mut Memory) -> ExecResult {
+    built_ins::wrapper_2_ret(built_ins::impls::format_time, "formatTime", mem)?;
+    ok_pc!(pc.offset(isize_of!(FORMATTIME)))
+}
+
+pub unsafe fn exec_formatutc(pc: *const u8, mem: &mut Memory) -> ExecResult {
+    built_ins::wrapper_2_ret(built_ins::impls::format_utc, "formatUtc", mem)?;
+    ok_pc!(pc.offset(isize_of!(FORMATUTC)))
+}
+
+pub unsafe fn exec_parsetime(pc: *const u8, mem: &mut Memory) -> ExecResult {
+    built_ins::wrapper_2_ret(built_ins::impls::parse_time, "parseTime", mem)?;
+    ok_pc!(pc.offset(isize_of!(PARSETIME)))
+}
+
+pub unsafe fn exec_parseutc(pc: *const u8, mem: &mut Memory) -> ExecResult {
+    built_ins::wrapper_2_ret(built_ins::impls::parse_utc, "parseUtc", mem)?;
+    ok_pc!(pc.offset(isize_of!(PARSEUTC)))
+}
+
+pub unsafe fn exec_duration(pc: *const u8, mem: &mut Memory) -> ExecResult {
+    built_ins::wrapper_1_ret(built_ins::impls::duration, "duration", mem)?;
+    ok_pc!(pc.offset(isize_of!(DURATION)))
+}
+
+pub unsafe fn exec_formatduration(pc: *const u8, mem: &mut Memory) -> ExecResult {
+    built_ins::wrapper_1_ret(built_ins::impls::format_duration, "formatDuration", mem)?;
+    ok_pc!(pc.offset(isize_of!(FORMATDURATION)))
+}
+
+pub unsafe fn exec_sleep(pc: *const u8, mem: &mut Memory) -> ExecResult {
+    built_ins::wrapper_1_ret(built_ins::impls::sleep, "sleep", mem)?;
+    ok_pc!(pc.offset(isize_of!(SLEEP)))
+}
+
+pub unsafe fn exec_measure(pc: *const u8, mem: &mut Memory) -> ExecResult {
+    built_ins::wrapper_mem_0_ret(built_ins::impls::measure, "measure", mem)?;
+    ok_pc!(pc.offset(isize_of!(MEASURE)))
+}
+
+pub unsafe fn exec_parseint(pc: *const u8, mem: &mut Memory) -> ExecResult {
+    built_ins::wrapper_1_ret(built_ins::impls::parse_int, "parseInt", mem)?;
+    ok_pc!(pc.offset(isize_of!(PARSEINT)))
+}
+
+pub unsafe fn exec_parseintor(pc: *const u8, mem: &mut Memory) -> ExecResult {
+    built_ins::wrapper_2_ret(built_ins::impls::parse_int_or, "parseIntOr", mem)?;
+    ok_pc!(pc.offset(isize_of!(PARSEINTOR)))
+}
+
+pub unsafe fn exec_parseintradix(pc: *const u8, mem: &mut Memory) -> ExecResult {
+    built_ins::wrapper_2_ret(built_ins::impls::parse_int_radix, "parseIntRadix", mem)?;
+    ok_pc!(pc.offset(isize_of!(PARSEINTRADIX)))
+}
+
+pub unsafe fn exec_parsefloat(pc: *const u8, mem: &mut Memory) -> ExecResult {
+    built_ins::wrapper_1_ret(built_ins::impls::parse_float, "parseFloat", mem)?;
+    ok_pc!(pc.offset(isize_of!(PARSEFLOAT)))
+}
+
+pub unsafe fn exec_parsefloator(pc: *const u8, mem: &mut Memory) -> ExecResult {
+    built_ins::wrapper_2_ret(built_ins::impls::parse_float_or, "parseFloatOr", mem)?;
+    ok_pc!(pc.offset(isize_of!(PARSEFLOATOR)))
+}
+
+pub unsafe fn exec_tofloat(pc: *const u8, mem: &mut Memory) -> ExecResult {
+    built_ins::wrapper_1_ret(built_ins::impls::to_float, "toFloat", mem)?;
+    ok_pc!(pc.offset(isize_of!(TOFLOAT)))
+}
+
+pub unsafe fn exec_fixed(pc: *const u8, mem: &mut Memory) -> ExecResult {
+    built_ins::wrapper_2_ret(built_ins::impls::fixed, "fixed", mem)?;
+    ok_pc!(pc.offset(isize_of!(FIXED)))
+}
+
+pub unsafe fn exec_padleft(pc: *const u8, mem: &mut Memory) -> ExecResult {
+    built_ins::wrapper_3_ret(built_ins::impls::pad_left, "padLeft", mem)?;
+    ok_pc!(pc.offset(isize_of!(PADLEFT)))
+}
+
+pub unsafe fn exec_padright(pc: *const u8, mem: &mut Memory) -> ExecResult {
+    built_ins::wrapper_3_ret(built_ins::impls::pad_right, "padRight", mem)?;
+    ok_pc!(pc.offset(isize_of!(PADRIGHT)))
+}
+
+pub unsafe fn exec_formatbytes(pc: *const u8, mem: &mut Memory) -> ExecResult {
+    built_ins::wrapper_1_ret(built_ins::impls::format_bytes, "formatBytes", mem)?;
+    ok_pc!(pc.offset(isize_of!(FORMATBYTES)))
+}
+
+pub unsafe fn exec_map(pc: *const u8, mem: &mut Memory) -> ExecResult {
+    iteration::start(mem, IterKind::Map)?;
+    ok_pc!(pc.offset(isize_of!(MAP)))
+}
+
+pub unsafe fn exec_filter(pc: *const u8, mem: &mut Memory) -> ExecResult {
+    iteration::start(mem, IterKind::Filter)?;
+    ok_pc!(pc.offset(isize_of!(FILTER)))
+}
+
+pub unsafe fn exec_fold(pc: *const u8, mem: &mut Memory) -> ExecResult {
+    iteration::start(mem, IterKind::Fold)?;
+    ok_pc!(pc.offset(isize_of!(FOLD)))
+}
+
+pub unsafe fn exec_each(pc: *const u8, mem: &mut Memory) -> ExecResult {
+    iteration::start(mem, IterKind::Each)?;
+    ok_pc!(pc.offset(isize_of!(EACH)))
+}
+
+pub unsafe fn exec_for(pc: *const u8, mem: &mut Memory) -> ExecResult {
+    iteration::start(mem, IterKind::Each)?;
+    ok_pc!(pc.offset(isize_of!(FOR)))
+}
+
+pub unsafe fn exec_any(pc: *const u8, mem: &mut Memory) -> ExecResult {
+    iteration::start(mem, IterKind::Any)?;
+    ok_pc!(pc.offset(isize_of!(ANY)))
+}
+
+pub unsafe fn exec_all(pc: *const u8, mem: &mut Memory) -> ExecResult {
+    iteration::start(mem, IterKind::All)?;
+    ok_pc!(pc.offset(isize_of!(ALL)))
+}
+
+pub unsafe fn exec_sortby(pc: *const u8, mem: &mut Memory) -> ExecResult {
+    iteration::start(mem, IterKind::SortBy)?;
+    ok_pc!(pc.offset(isize_of!(SORTBY)))
+}
+
+pub unsafe fn exec_mapindexed(pc: *const u8, mem: &mut Memory) -> ExecResult {
+    iteration::start(mem, IterKind::MapIndexed)?;
+    ok_pc!(pc.offset(isize_of!(MAPINDEXED)))
+}
+
+pub unsafe fn exec_sort(pc: *const u8, mem: &mut Memory) -> ExecResult {
+    built_ins::wrapper_1_ret(built_ins::impls::sort, "sort", mem)?;
+    ok_pc!(pc.offset(isize_of!(SORT)))
+}
+
+pub unsafe fn exec_unique(pc: *const u8, mem: &mut Memory) -> ExecResult {
+    built_ins::wrapper_1_ret(built_ins::impls::unique, "unique", mem)?;
+    ok_pc!(pc.offset(isize_of!(UNIQUE)))
+}
+
+pub unsafe fn exec_zip(pc: *const u8, mem: &mut Memory) -> ExecResult {
+    built_ins::wrapper_2_ret(built_ins::impls::zip, "zip", mem)?;
+    ok_pc!(pc.offset(isize_of!(ZIP)))
+}
+
+pub unsafe fn exec_enumerate(pc: *const u8, mem: &mut Memory) -> ExecResult {
+    built_ins::wrapper_1_ret(built_ins::impls::enumerate, "enumerate", mem)?;
+    ok_pc!(pc.offset(isize_of!(ENUMERATE)))
+}
+
+pub unsafe fn exec_flatten(pc: *const u8, mem: &mut Memory) -> ExecResult {
+    built_ins::wrapper_1_ret(built_ins::impls::flatten, "flatten", mem)?;
+    ok_pc!(pc.offset(isize_of!(FLATTEN)))
+}
+
+pub unsafe fn exec_filelines(pc: *const u8, mem: &mut Memory) -> ExecResult {
+    built_ins::wrapper_1_ret(built_ins::impls::file_lines, "fileLines", mem)?;
+    ok_pc!(pc.offset(isize_of!(FILELINES)))
+}
+
+pub unsafe fn exec_outputlines(pc: *const u8, mem: &mut Memory) -> ExecResult {
+    built_ins::wrapper_mem_1_var_ret(built_ins::impls::output_lines, "outputLines", mem)?;
+    ok_pc!(pc.offset(isize_of!(OUTPUTLINES)))
+}
+
+pub unsafe fn exec_range(pc: *const u8, mem: &mut Memory) -> ExecResult {
+    built_ins::wrapper_2_ret(built_ins::impls::range, "range", mem)?;
+    ok_pc!(pc.offset(isize_of!(RANGE)))
+}
+
+pub unsafe fn exec_take(pc: *const u8, mem: &mut Memory) -> ExecResult {
+    built_ins::wrapper_mem_2_ret(built_ins::impls::take, "take", mem)?;
+    ok_pc!(pc.offset(isize_of!(TAKE)))
+}
+
+pub unsafe fn exec_skip(pc: *const u8, mem: &mut Memory) -> ExecResult {
+    built_ins::wrapper_mem_2_ret(built_ins::impls::skip, "skip", mem)?;
+    ok_pc!(pc.offset(isize_of!(SKIP)))
+}
+
+pub unsafe fn exec_tolist(pc: *const u8, mem: &mut Memory) -> ExecResult {
+    built_ins::wrapper_mem_1_ret(built_ins::impls::to_list, "toList", mem)?;
+    ok_pc!(pc.offset(isize_of!(TOLIST)))
+}
+
+pub unsafe fn exec_rangeinclusive(pc: *const u8, mem: &mut Memory) -> ExecResult {
+    built_ins::wrapper_2_ret(built_ins::impls::range_inclusive, "rangeInclusive", mem)?;
+    ok_pc!(pc.offset(isize_of!(RANGEINCLUSIVE)))
+}
+
+pub unsafe fn exec_rangestep(pc: *const u8, mem: &mut Memory) -> ExecResult {
+    built_ins::wrapper_mem_2_ret(built_ins::impls::range_step, "rangeStep", mem)?;
+    ok_pc!(pc.offset(isize_of!(RANGESTEP)))
+}
+
+pub unsafe fn exec_isin(pc: *const u8, mem: &mut Memory) -> ExecResult {
+    built_ins::wrapper_mem_2_ret(built_ins::impls::is_in, "isIn", mem)?;
+    ok_pc!(pc.offset(isize_of!(ISIN)))
+}
+
+pub unsafe fn exec_index(pc: *const u8, mem: &mut Memory) -> ExecResult {
+    built_ins::wrapper_mem_2_ret(built_ins::impls::index, "index", mem)?;
+    ok_pc!(pc.offset(isize_of!(INDEX)))
+}
+
+pub unsafe fn exec_execredirected(pc: *const u8, mem: &mut Memory) -> ExecResult {
+    built_ins::wrapper_4_var(built_ins::impls::exec_redirected, "execRedirected", mem)?;
+    ok_pc!(pc.offset(isize_of!(EXECREDIRECTED)))
+}
+
+pub unsafe fn exec_spawnredirected(pc: *const u8, mem: &mut Memory) -> ExecResult {
+    built_ins::wrapper_mem_4_var_ret(built_ins::impls::spawn_redirected, "spawnRedirected", mem)?;
+    ok_pc!(pc.offset(isize_of!(SPAWNREDIRECTED)))
+}
+
+pub unsafe fn exec_orelse(pc: *const u8, mem: &mut Memory) -> ExecResult {
+    built_ins::wrapper_2_ret(built_ins::impls::or_else, "orElse", mem)?;
+    ok_pc!(pc.offset(isize_of!(ORELSE)))
+}
+
+pub unsafe fn exec_issome(pc: *const u8, mem: &mut Memory) -> ExecResult {
+    built_ins::wrapper_1_ret(built_ins::impls::is_some, "isSome", mem)?;
+    ok_pc!(pc.offset(isize_of!(ISSOME)))
+}
+
+pub unsafe fn exec_first(pc: *const u8, mem: &mut Memory) -> ExecResult {
+    built_ins::wrapper_1_ret(built_ins::impls::first, "first", mem)?;
+    ok_pc!(pc.offset(isize_of!(FIRST)))
+}
+
+pub unsafe fn exec_second(pc: *const u8, mem: &mut Memory) -> ExecResult {
+    built_ins::wrapper_1_ret(built_ins::impls::second, "second", mem)?;
+    ok_pc!(pc.offset(isize_of!(SECOND)))
+}
+
 pub unsafe fn exec_tostr(pc: *const u8, mem: &mut Memory) -> ExecResult {
     let val = mem.stack.pop().unwrap();
     mem.push_heap(val.to_string());
     ok_pc!(pc.offset(isize_of!(TOSTR)))
 }
 
+pub unsafe fn exec_toregex(pc: *const u8, mem: &mut Memory) -> ExecResult {
+    let val = mem.stack.pop().unwrap();
+    let pattern: *const String = val.rtry_into().ok_or_else(|| Error::TypeError {
+        accessed_data: val.clone(),
+        expected_type: "Str",
+    })?;
+    mem.regex(&*pattern)?;
+    mem.push_heap(Value::Regex((*pattern).clone()));
+    ok_pc!(pc.offset(isize_of!(TOREGEX)))
+}
+
 pub unsafe fn exec_tobool(pc: *const u8, mem: &mut Memory) -> ExecResult {
     let val = mem.stack.pop().unwrap();
     match val {
@@ -143,7 +892,7 @@ pub unsafe fn exec_pushprimitive(pc: *const u8, mem: &mut Memory) -> ExecResult
 
 pub unsafe fn exec_repushstackentry(pc: *const u8, mem: &mut Memory) -> ExecResult {
     let idx = get_body!(RepushStackEntry, pc.offset(2));
-    mem.copy_stack_entry_to_top(*idx);
+    mem.copy_stack_entry_to_top(mem.frame_base + *idx);
     ok_pc!(pc.offset(isize_of!(REPUSHSTACKENTRY)))
 }
 
@@ -164,6 +913,157 @@ pub unsafe fn exec_pop(pc: *const u8, mem: &mut Memory) -> ExecResult {
     ok_pc!(pc.offset(isize_of!(POP)))
 }
 
+pub unsafe fn exec_spreadvec(pc: *const u8, mem: &mut Memory) -> ExecResult {
+    let info = get_body!(SpreadVec, pc.offset(2));
+    let len = mem
+        .spread_vec(mem.frame_base + info.stack_idx)
+        .ok_or_else(|| Error::UnexpectedStackEntry {
+            index: info.stack_idx,
+            msg: "only lists can be spliced".into(),
+        })?;
+    if info.exact {
+        rt_assert!(
+            len == info.min_len,
+            "spliced list has {} elements, but exactly {} are needed",
+            len,
+            info.min_len
+        );
+    } else {
+        rt_assert!(
+            len >= info.min_len,
+            "spliced list has {} elements, but at least {} are needed",
+            len,
+            info.min_len
+        );
+    }
+    ok_pc!(pc.offset(isize_of!(SPREADVEC)))
+}
+
+pub unsafe fn exec_pushvarargcount(pc: *const u8, mem: &mut Memory) -> ExecResult {
+    let start = get_body!(PushVarArgCount, pc.offset(2));
+    let count = mem.stack.len() - (mem.frame_base + *start);
+    mem.push_stack(CopyValue::from(count));
+    ok_pc!(pc.offset(isize_of!(PUSHVARARGCOUNT)))
+}
+
+pub unsafe fn exec_truncatestack(pc: *const u8, mem: &mut Memory) -> ExecResult {
+    let len = get_body!(TruncateStack, pc.offset(2));
+    mem.stack.truncate(mem.frame_base + *len);
+    ok_pc!(pc.offset(isize_of!(TRUNCATESTACK)))
+}
+
+pub unsafe fn exec_leavescope(pc: *const u8, mem: &mut Memory) -> ExecResult {
+    mem.leave_scope()?;
+    ok_pc!(pc.offset(isize_of!(LEAVESCOPE)))
+}
+
+pub unsafe fn exec_leavemeasure(pc: *const u8, mem: &mut Memory) -> ExecResult {
+    let ms = mem.leave_measure()?;
+    let body = iteration::copy(mem.stack_back(0))?;
+    mem.store(Storable::VecOnHeap(vec![body, ms.into()]));
+    ok_pc!(pc.offset(isize_of!(LEAVEMEASURE)))
+}
+
+pub unsafe fn exec_iternext(pc: *const u8, mem: &mut Memory) -> ExecResult {
+    let offset = get_body!(IterNext, pc.offset(2));
+    if iteration::next(mem)? {
+        ok_pc!(pc.offset(isize_of!(ITERNEXT)))
+    } else {
+        ok_pc!(pc.offset(*offset))
+    }
+}
+
+pub unsafe fn exec_itercollect(pc: *const u8, mem: &mut Memory) -> ExecResult {
+    iteration::collect(mem)?;
+    ok_pc!(pc.offset(isize_of!(ITERCOLLECT)))
+}
+
+pub unsafe fn exec_iterend(pc: *const u8, mem: &mut Memory) -> ExecResult {
+    iteration::finish(mem)?;
+    ok_pc!(pc.offset(isize_of!(ITEREND)))
+}
+
+pub unsafe fn exec_lazy(pc: *const u8, mem: &mut Memory) -> ExecResult {
+    let info = get_body!(Lazy, pc.offset(2));
+    sequence::lazy(mem, info.kind, info.captured, pc.offset(info.offset))?;
+    ok_pc!(pc.offset(isize_of!(LAZY)))
+}
+
+pub unsafe fn exec_fragmentend(_: *const u8, _: &mut Memory) -> ExecResult {
+    // the code of fragments is jumped over, see sequence::Fragment
+    Err(rt_err!(
+        "Reached the end of a fragment outside of a sequence"
+    ))
+}
+
+pub unsafe fn exec_parseargs(pc: *const u8, mem: &mut Memory) -> ExecResult {
+    let idx = get_body!(ParseArgs, pc.offset(2));
+    let spec = &mem.arg_specs[*idx];
+    let script_name = mem.args.first().map(String::as_str).unwrap_or_default();
+    match args::parse(spec, mem.args.get(1..).unwrap_or_default()) {
+        Ok(values) => {
+            for v in values {
+                mem.store(v);
+            }
+            ok_pc!(pc.offset(isize_of!(PARSEARGS)))
+        }
+        Err(args::ArgsError::Help) => {
+            print!("{}", args::help_text(spec, script_name));
+            Ok(ExecOutcome::ExitCode(0))
+        }
+        Err(args::ArgsError::Usage(msg)) => {
+            eprintln!("error: {}\n{}", msg, args::usage(spec, script_name));
+            eprintln!("try '{} --help' for more information", script_name);
+            Ok(ExecOutcome::ExitCode(2))
+        }
+    }
+}
+
+pub unsafe fn exec_selectmode(pc: *const u8, mem: &mut Memory) -> ExecResult {
+    let idx = get_body!(SelectMode, pc.offset(2));
+    let spec = &mem.mode_specs[*idx];
+    let script_name = mem.args.first().cloned().unwrap_or_default();
+    match args::select_mode(spec, mem.args.get(1..).unwrap_or_default()) {
+        Ok((mode, pos)) => {
+            // the mode is part of the name in the usage of the mode
+            let mode_name = mem.args.remove(1 + pos);
+            mem.args[0] = format!("{} {}", script_name, mode_name);
+            mem.selected_mode = mode;
+            ok_pc!(pc.offset(isize_of!(SELECTMODE)))
+        }
+        Err(args::ArgsError::Help) => {
+            print!("{}", args::modes_help_text(spec, &script_name));
+            Ok(ExecOutcome::ExitCode(0))
+        }
+        Err(args::ArgsError::Usage(msg)) => {
+            eprintln!("error: {}\n{}", msg, args::modes_usage(spec, &script_name));
+            eprintln!("try '{} --help' for more information", script_name);
+            Ok(ExecOutcome::ExitCode(2))
+        }
+    }
+}
+
+pub unsafe fn exec_jump(pc: *const u8, _: &mut Memory) -> ExecResult {
+    let offset = get_body!(Jump, pc.offset(2));
+    ok_pc!(pc.offset(*offset))
+}
+
+pub unsafe fn exec_jumpunlessmode(pc: *const u8, mem: &mut Memory) -> ExecResult {
+    let jump = get_body!(JumpUnlessMode, pc.offset(2));
+    if mem.selected_mode == jump.mode {
+        ok_pc!(pc.offset(isize_of!(JUMPUNLESSMODE)))
+    } else {
+        ok_pc!(pc.offset(jump.offset))
+    }
+}
+
+pub unsafe fn exec_pushglobal(pc: *const u8, mem: &mut Memory) -> ExecResult {
+    let global = get_body!(PushGlobal, pc.offset(2));
+    let val = globals::value(*global, mem)?;
+    mem.store(val);
+    ok_pc!(pc.offset(isize_of!(PUSHGLOBAL)))
+}
+
 pub unsafe fn exec_popfree(pc: *const u8, mem: &mut Memory) -> ExecResult {
     mem.pop_free();
     ok_pc!(pc.offset(isize_of!(POPFREE)))
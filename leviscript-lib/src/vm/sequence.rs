@@ -0,0 +1,305 @@
+//! Lazy sequences, their elements are produced one at a time, when they are consumed.
+//!
+//! A sequence is a [Value::Seq](crate::core::Value::Seq) on the heap, which refers to its state
+//! in the [Memory](crate::vm::Memory). The state can't be copied, so each element is produced only
+//! once: a sequence that is consumed a second time continues where the first time stopped.
+//! Freeing the value closes the file, or stops the program behind it.
+//!
+//! map, filter and mapIndexed make a lazy sequence of a sequence, which runs their fragment for
+//! each element that is pulled from it, see [Fragment].
+
+use crate::core::*;
+use crate::vm::{iteration, rt_assert, rt_err, signals, Error, ExecOutcome, IterKind, Memory};
+use crate::vm::{Result, Storable};
+
+use std::fs::File;
+use std::io::{BufRead, BufReader, Lines};
+use std::process::{Child, ChildStdout, Command, Stdio};
+
+#[derive(Debug)]
+pub enum Sequence {
+    /// the lines of a file, without the line endings
+    FileLines {
+        path: String,
+        lines: Lines<BufReader<File>>,
+    },
+    /// the lines a program writes to stdout
+    Output(Output),
+    /// the lines of stdin, without the line endings. Lines that aren't consumed can still be read
+    /// by the other built ins that read from stdin
+    StdinLines,
+    /// the ints from next up to end, every step one. A negative step counts down
+    Range {
+        next: i64,
+        end: i64,
+        step: i64,
+        inclusive: bool,
+    },
+    /// the first n elements of the sequence
+    Take(Box<Sequence>, usize),
+    /// the sequence without its first n elements, they are skipped once the first one is needed
+    Skip(Box<Sequence>, usize),
+    /// the results of the fragment for the elements of the sequence
+    Map(Box<Sequence>, Fragment),
+    /// the elements of the sequence, for which the fragment returns true
+    Filter(Box<Sequence>, Fragment),
+    /// like map, the fragment also gets the index of the element, which is the second value
+    MapIndexed(Box<Sequence>, Fragment, usize),
+}
+
+/// The fragment of a lazy sequence. Its code is jumped over where the sequence is made, and runs
+/// whenever an element is pulled, until [OpCode::FragmentEnd].
+///
+/// It runs in its own frame, see [Memory::frame_base]. The frame starts with copies of the values
+/// of the symbols the fragment refers to, followed by its arguments. The symbols might be gone by
+/// then, so the sequence keeps copies of their values
+#[derive(Debug)]
+pub struct Fragment {
+    pc: *const u8,
+    /// they are owned by the sequence
+    captured: Vec<RuntimeData>,
+}
+
+#[derive(Debug)]
+pub struct Output {
+    program: String,
+    child: Child,
+    lines: Lines<BufReader<ChildStdout>>,
+    /// set once the program ended, and its exit code was checked
+    done: bool,
+}
+
+impl Sequence {
+    pub fn file_lines(path: &str) -> Result<Self> {
+        let file = File::open(path).map_err(|e| rt_err!("Opening {}: {}", path, e))?;
+        Ok(Sequence::FileLines {
+            path: path.into(),
+            lines: BufReader::new(file).lines(),
+        })
+    }
+
+    /// starts the program, it runs alongside the script, until its output is consumed
+    pub fn output(program: &str, args: &[&str]) -> Result<Self> {
+        let mut child = Command::new(program)
+            .args(args)
+            .stdout(Stdio::piped())
+            .spawn()
+            .map_err(|e| rt_err!("Spawning {}: {}", program, e))?;
+        let stdout = child.stdout.take().unwrap();
+        Ok(Sequence::Output(Output {
+            program: program.into(),
+            child,
+            lines: BufReader::new(stdout).lines(),
+            done: false,
+        }))
+    }
+
+    /// whether a range produces the int, the range isn't consumed. None for other sequences
+    pub fn range_contains(&self, i: i64) -> Option<bool> {
+        let Sequence::Range {
+            next,
+            end,
+            step,
+            inclusive,
+        } = *self
+        else {
+            return None;
+        };
+        let (low, high) = if step > 0 { (next, end) } else { (end, next) };
+        let within = low <= i && i <= high && (inclusive || i != end);
+        // i128, so that the distance doesn't overflow
+        Some(within && (i as i128 - next as i128) % step as i128 == 0)
+    }
+
+    /// produces the next element, or None, if there are no more elements. The sequence must not
+    /// be in the memory, see [Memory::next_elem]
+    pub unsafe fn next_elem(&mut self, mem: &mut Memory) -> Result<Option<Storable>> {
+        match self {
+            Sequence::FileLines { path, lines } => lines
+                .next()
+                .transpose()
+                .map(|l| l.map(Storable::from))
+                .map_err(|e| rt_err!("Reading {}: {}", path, e)),
+            Sequence::Output(output) => output.next_line().map(|l| l.map(Storable::from)),
+            Sequence::StdinLines => std::io::stdin()
+                .lines()
+                .next()
+                .transpose()
+                .map(|l| l.map(Storable::from))
+                .map_err(|e| rt_err!("Reading from {}: {}", Stream::In, e)),
+            Sequence::Range {
+                next,
+                end,
+                step,
+                inclusive,
+            } => {
+                let ended = match (*step > 0, *inclusive) {
+                    (true, false) => next >= end,
+                    (true, true) => next > end,
+                    (false, false) => next <= end,
+                    (false, true) => next < end,
+                };
+                if ended {
+                    return Ok(None);
+                }
+                let current = *next;
+                match next.checked_add(*step) {
+                    Some(n) => *next = n,
+                    // an overflow ends the range
+                    None => (*end, *inclusive) = (current, false),
+                }
+                Ok(Some(current.into()))
+            }
+            Sequence::Take(_, 0) => Ok(None),
+            Sequence::Take(seq, n) => {
+                *n -= 1;
+                seq.next_elem(mem)
+            }
+            Sequence::Skip(seq, n) => {
+                while *n > 0 {
+                    *n -= 1;
+                    if seq.next_elem(mem)?.is_none() {
+                        return Ok(None);
+                    }
+                }
+                seq.next_elem(mem)
+            }
+            Sequence::Map(seq, fragment) => match seq.next_elem(mem)? {
+                Some(elem) => fragment.call(mem, vec![elem]).map(Some),
+                None => Ok(None),
+            },
+            Sequence::Filter(seq, fragment) => {
+                while let Some(elem) = seq.next_elem(mem)? {
+                    let elem = mem.alloc(elem);
+                    let keep = fragment.call(mem, vec![iteration::copy(&elem)?])?;
+                    if matches!(
+                        keep,
+                        Storable::OnStack(Data::CopyVal(CopyValue::Bool(true)))
+                    ) {
+                        return Ok(Some(Storable::OnStack(elem)));
+                    }
+                    mem.free(elem);
+                }
+                Ok(None)
+            }
+            Sequence::MapIndexed(seq, fragment, idx) => match seq.next_elem(mem)? {
+                Some(elem) => {
+                    let i = *idx as i64;
+                    *idx += 1;
+                    fragment.call(mem, vec![i.into(), elem]).map(Some)
+                }
+                None => Ok(None),
+            },
+        }
+    }
+
+    /// the values the fragments of the sequence own, they need to be freed with it
+    pub fn into_captured(self) -> Vec<RuntimeData> {
+        match self {
+            Sequence::Take(seq, _) | Sequence::Skip(seq, _) => seq.into_captured(),
+            Sequence::Map(seq, fragment)
+            | Sequence::Filter(seq, fragment)
+            | Sequence::MapIndexed(seq, fragment, _) => {
+                let mut captured = seq.into_captured();
+                captured.extend(fragment.captured);
+                captured
+            }
+            Sequence::FileLines { .. }
+            | Sequence::Output(_)
+            | Sequence::StdinLines
+            | Sequence::Range { .. } => vec![],
+        }
+    }
+}
+
+/// Replaces the sequence below the captured values on the stack by a lazy one. It takes copies of
+/// them, see [OpCode::Lazy]
+pub unsafe fn lazy(mem: &mut Memory, kind: IterKind, captured: usize, pc: *const u8) -> Result<()> {
+    let mut values = vec![];
+    for i in (0..captured).rev() {
+        let copied = iteration::copy(mem.stack_back(i))?;
+        values.push(mem.alloc(copied));
+    }
+    let Some(SeqData(idx)) = mem.stack_back(captured).rtry_into() else {
+        return Err(rt_err!(
+            "Expected a sequence, found: {}",
+            mem.stack_back(captured)
+        ));
+    };
+    let seq = Box::new(mem.take_sequence(idx)?);
+    let fragment = Fragment {
+        pc,
+        captured: values,
+    };
+    let lazy = match kind {
+        IterKind::Map => Sequence::Map(seq, fragment),
+        IterKind::Filter => Sequence::Filter(seq, fragment),
+        IterKind::MapIndexed => Sequence::MapIndexed(seq, fragment, 0),
+        _ => return Err(rt_err!("{:?} doesn't make a lazy sequence", kind)),
+    };
+    mem.store(Storable::SeqOnHeap(lazy));
+    Ok(())
+}
+
+impl Fragment {
+    /// runs the code of the fragment with the arguments, and returns its result
+    unsafe fn call(&self, mem: &mut Memory, args: Vec<Storable>) -> Result<Storable> {
+        let frame_base = mem.stack.len();
+        for value in &self.captured {
+            let copied = iteration::copy(value)?;
+            mem.store(copied);
+        }
+        for arg in args {
+            mem.store(arg);
+        }
+        let outer_base = std::mem::replace(&mut mem.frame_base, frame_base);
+        let mut pc = self.pc;
+        while *(pc as *const u16) != OpCode::FRAGMENTEND {
+            signals::check()?;
+            match OpCode::dispatch_discriminant(*(pc as *const u16), pc, mem)? {
+                ExecOutcome::Pc(next) => pc = next,
+                ExecOutcome::ExitCode(_) => return Err(rt_err!("Exited in a fragment")),
+            }
+        }
+        mem.frame_base = outer_base;
+        // the frame was collapsed to the result, which it owns
+        Ok(Storable::OnStack(mem.stack.pop().unwrap()))
+    }
+}
+
+impl Output {
+    /// fails at the end of the output, if the program didn't succeed
+    fn next_line(&mut self) -> Result<Option<String>> {
+        if self.done {
+            return Ok(None);
+        }
+        match self.lines.next() {
+            Some(line) => line
+                .map(Some)
+                .map_err(|e| rt_err!("Reading the output of {}: {}", self.program, e)),
+            None => {
+                self.done = true;
+                let stat = self
+                    .child
+                    .wait()
+                    .map_err(|e| rt_err!("Waiting for {}: {}", self.program, e))?;
+                rt_assert!(
+                    stat.success(),
+                    "{} did not execute successfully",
+                    self.program
+                );
+                Ok(None)
+            }
+        }
+    }
+}
+
+impl Drop for Output {
+    fn drop(&mut self) {
+        if !self.done {
+            // the program might have finished in the meantime, so this may fail
+            let _ = self.child.kill();
+            let _ = self.child.wait();
+        }
+    }
+}
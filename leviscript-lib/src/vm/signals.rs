@@ -0,0 +1,48 @@
+//! SIGINT and SIGTERM stop the script like a runtime error, so that the scoped built ins undo
+//! their changes, and temporary paths are deleted, see [ScopeGuard](crate::vm::ScopeGuard).
+//!
+//! The handler only records the signal, the [Runner](crate::core::Runner) checks for it before
+//! each opcode. A program the script waits for isn't interrupted by that. SIGINT from a terminal
+//! is sent to the program too, for other signals the script stops once the program ended.
+
+use crate::vm::{Error, Result};
+
+use std::sync::atomic::{AtomicI32, Ordering};
+
+/// the signal that was received, 0 if there was none
+static RECEIVED: AtomicI32 = AtomicI32::new(0);
+
+extern "C" fn on_signal(sig: libc::c_int) {
+    RECEIVED.store(sig, Ordering::SeqCst);
+    // a second signal stops the script right away, e.g. if it hangs while cleaning up
+    unsafe { libc::signal(sig, libc::SIG_DFL) };
+}
+
+/// installs the handlers. Signals that are ignored, like SIGINT for background jobs of a
+/// non-interactive shell, stay ignored
+pub fn install() {
+    for sig in [libc::SIGINT, libc::SIGTERM] {
+        unsafe {
+            let handler = on_signal as extern "C" fn(libc::c_int) as libc::sighandler_t;
+            if libc::signal(sig, handler) == libc::SIG_IGN {
+                libc::signal(sig, libc::SIG_IGN);
+            }
+        }
+    }
+}
+
+/// the signal that was received, if any
+pub fn received() -> Option<i32> {
+    match RECEIVED.load(Ordering::SeqCst) {
+        0 => None,
+        sig => Some(sig),
+    }
+}
+
+/// fails if a signal was received, for built ins that block for a long time
+pub fn check() -> Result<()> {
+    match received() {
+        Some(sig) => Err(Error::Interrupted(sig)),
+        None => Ok(()),
+    }
+}
@@ -3,7 +3,10 @@ use glob::glob;
 use std::result::Result as StdResult;
 
 use std::fs;
-use std::process::Command;
+use std::path::Path;
+use std::process::{Command, Output};
+
+const LEVIS: &str = "../interpreter/target/release/levis";
 
 fn main() -> Result<()> {
     compile_levis().context("compiling interpreter")?;
@@ -15,11 +18,7 @@ fn main() -> Result<()> {
     for (script, expected_output) in scripts.iter().zip(outputs) {
         match fs::read_to_string(&expected_output) {
             Ok(expected_output) => {
-                let output_bytes = Command::new("../interpreter/target/release/levis")
-                    .arg(script)
-                    .output()
-                    .context(format!("running script {}", script.display()))?
-                    .stdout;
+                let output_bytes = run_script(script)?.stdout;
                 let output = String::from_utf8(output_bytes)?;
                 if output == expected_output {
                     println!("{}: passed", script.display());
@@ -32,9 +31,41 @@ fn main() -> Result<()> {
             }
         }
     }
+
+    // fixtures with an .err file are expected to fail, the file holds their stderr
+    // followed by the exit code. If there is an .out file as well, stdout is compared too
+    let errors: Vec<_> = glob("tests/fixtures/**/*.err")?.collect::<StdResult<_, _>>()?;
+    for expected_errors in errors {
+        let script = expected_errors.with_extension("les");
+        let output = run_script(&script)?;
+        let errors = format!(
+            "{}exit code: {}\n",
+            String::from_utf8(output.stderr)?,
+            output.status.code().unwrap_or(-1)
+        );
+        let stdout = String::from_utf8(output.stdout)?;
+        let expected_stdout = fs::read_to_string(script.with_extension("out")).unwrap_or_default();
+        if errors == fs::read_to_string(&expected_errors)? && stdout == expected_stdout {
+            println!("{}: passed", script.display());
+        } else {
+            println!(
+                "{}: failed\nactual output:\n{}actual errors:\n{}",
+                script.display(),
+                stdout,
+                errors
+            );
+        }
+    }
     Ok(())
 }
 
+fn run_script(script: &Path) -> Result<Output> {
+    Command::new(LEVIS)
+        .arg(script)
+        .output()
+        .context(format!("running script {}", script.display()))
+}
+
 fn compile_levis() -> Result<()> {
     let st = Command::new("cargo")
         .args(["build", "--release"])